@@ -1,21 +1,169 @@
 use std::{
+    fmt::Display,
     net::{SocketAddr, TcpStream, ToSocketAddrs},
     process::Stdio,
     time::{Duration, Instant},
 };
 
-use numpy::{ndarray::Array, PyArray, PyArray2, PyReadonlyArray1, PyReadonlyArray2};
-use pyo3::{prelude::*, types::PyTuple};
-use super_simple_mesh_viewer::{run_rust, Communication, Message, Response, Vec3, View};
+use numpy::{
+    ndarray::{s, Array, Array2, Dimension, Ix1, Ix2},
+    Element, PyArray, PyReadonlyArray1,
+};
+use pyo3::{
+    create_exception,
+    exceptions::{PyConnectionError, PyException, PyTypeError, PyValueError},
+    prelude::*,
+    types::{PyDict, PyTuple},
+};
+use super_simple_mesh_viewer::{
+    config::{ConfigError, HOST_ENV, PORT_ENV},
+    run_rust, ColormapSettings, Communication, ErrorKind, Faces, FrameError, MaterialSettings,
+    MeshData, Message, MyCameraController, Options, Response, ServerInfo, Shading, Vec3, View,
+    DEFAULT_CREASE_ANGLE, DEFAULT_OBJECT,
+};
+
+create_exception!(ssmv, ViewerError, PyException);
+
+fn frame_error(e: FrameError) -> PyErr {
+    PyConnectionError::new_err(e.to_string())
+}
+
+/// Reads the next response, raising errors reported by the viewer.
+fn receive(stream: &mut TcpStream) -> PyResult<Response> {
+    match Response::receive(stream).map_err(frame_error)? {
+        Some(Response::Error { kind, message }) => Err(match kind {
+            ErrorKind::InvalidMesh | ErrorKind::InvalidSettings | ErrorKind::InvalidView => {
+                PyValueError::new_err(message)
+            }
+            ErrorKind::ProtocolMismatch | ErrorKind::Overloaded => ViewerError::new_err(message),
+        }),
+        Some(response) => Ok(response),
+        None => Err(PyConnectionError::new_err("viewer closed the connection")),
+    }
+}
+
+/// What `array` is, for error messages.
+fn describe(array: &PyAny) -> String {
+    match (array.getattr("ndim"), array.getattr("dtype")) {
+        (Ok(ndim), Ok(dtype)) => format!("a {}D {} array", ndim, dtype),
+        _ => format!("{}", array.get_type()),
+    }
+}
+
+/// Reads a float32 or float64 array of any memory layout as f32.
+fn float_array<D: Dimension>(array: &PyAny, name: &str) -> PyResult<Array<f32, D>> {
+    if let Ok(array) = array.downcast::<PyArray<f32, D>>() {
+        return Ok(array.readonly().as_array().to_owned());
+    }
+    if let Ok(array) = array.downcast::<PyArray<f64, D>>() {
+        return Ok(array.readonly().as_array().mapv(|v| v as f32));
+    }
+    Err(PyTypeError::new_err(format!(
+        "{} must be a {}D float32 or float64 array, got {}",
+        name,
+        D::NDIM.unwrap_or_default(),
+        describe(array)
+    )))
+}
+
+/// Reads vertices of shape (N, 3), or (N, 2) for points in the z = 0 plane.
+fn verts_array(verts: &PyAny) -> PyResult<Array2<f32>> {
+    let verts = float_array::<Ix2>(verts, "verts")?;
+    if verts.ncols() != 2 {
+        return Ok(verts);
+    }
+    let mut padded = Array2::zeros((verts.nrows(), 3));
+    padded.slice_mut(s![.., ..2]).assign(&verts);
+    Ok(padded)
+}
+
+fn convert_indices<T, D>(indices: &PyArray<T, D>) -> PyResult<Array<i32, D>>
+where
+    T: Element + Copy + Display,
+    D: Dimension,
+    i32: TryFrom<T>,
+{
+    let indices = indices.readonly();
+    let indices = indices.as_array();
+    let mut converted = Array::zeros(indices.raw_dim());
+    for (converted, &index) in converted.iter_mut().zip(indices) {
+        *converted = i32::try_from(index)
+            .map_err(|_| PyValueError::new_err(format!("index {} is out of range", index)))?;
+    }
+    Ok(converted)
+}
+
+/// Reads indices of any integer dtype and memory layout as i32.
+fn index_array<D: Dimension>(array: &PyAny, name: &str) -> PyResult<Array<i32, D>> {
+    macro_rules! try_dtypes {
+        ($($t:ty),*) => {$(
+            if let Ok(array) = array.downcast::<PyArray<$t, D>>() {
+                return convert_indices(array);
+            }
+        )*};
+    }
+    try_dtypes!(i32, i64, u32, u64, i16, u16, i8, u8);
+    Err(PyTypeError::new_err(format!(
+        "{} must be a {}D integer array, got {}",
+        name,
+        D::NDIM.unwrap_or_default(),
+        describe(array)
+    )))
+}
+
+/// Reads faces given either as rows of corners, or as a flat array of
+/// corners split up by `offsets`.
+fn faces_array(faces: &PyAny, offsets: Option<&PyAny>) -> PyResult<Faces> {
+    match offsets {
+        None => Ok(Faces::Uniform(index_array::<Ix2>(faces, "faces")?)),
+        Some(offsets) => Ok(Faces::Polygons {
+            offsets: index_array::<Ix1>(offsets, "face_offsets")?,
+            indices: index_array::<Ix1>(faces, "faces")?,
+        }),
+    }
+}
+
+fn parse_shading(shading: &str, crease_angle: f32) -> PyResult<Shading> {
+    match shading {
+        "flat" => Ok(Shading::Flat),
+        "smooth" => Ok(Shading::Smooth),
+        "crease" => Ok(Shading::Crease(crease_angle)),
+        _ => Err(PyValueError::new_err(format!(
+            "unknown shading {:?}, expected \"flat\", \"smooth\" or \"crease\"",
+            shading
+        ))),
+    }
+}
+
+/// `from_env` with `host` and `port` overridden where given.
+fn options(
+    from_env: Result<Options, ConfigError>,
+    host: Option<String>,
+    port: Option<u16>,
+) -> PyResult<Options> {
+    let mut options = from_env.map_err(|e| PyValueError::new_err(e.to_string()))?;
+    if let Some(host) = host {
+        options.host = host;
+    }
+    if let Some(port) = port {
+        options.port = port;
+    }
+    Ok(options)
+}
 
 #[pyfunction]
-fn run() {
-    run_rust();
+#[pyo3(signature = (host = None, port = None, port_fallback = false))]
+fn run(host: Option<String>, port: Option<u16>, port_fallback: bool) -> PyResult<()> {
+    let mut options = options(Options::from_env(), host, port)?;
+    options.port_fallback = port_fallback;
+    run_rust(options).map_err(|e| PyConnectionError::new_err(e.to_string()))
 }
 
 #[pyclass]
 pub struct Connection {
     tcp: Option<TcpStream>,
+    options: Options,
+    server_info: Option<ServerInfo>,
 }
 
 fn retry(addr: &SocketAddr, timeout: Duration) -> Result<TcpStream, std::io::Error> {
@@ -34,60 +182,140 @@ fn retry(addr: &SocketAddr, timeout: Duration) -> Result<TcpStream, std::io::Err
 }
 
 impl Connection {
-    fn connect(&mut self) -> &mut TcpStream {
-        let addr = "localhost:6142".to_socket_addrs().unwrap().next().unwrap();
+    fn connect(&mut self) -> PyResult<()> {
+        let addr = self
+            .options
+            .addr()
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| {
+                PyConnectionError::new_err(format!("could not resolve {}", self.options.addr()))
+            })?;
         let timeout = std::time::Duration::from_millis(50);
         let boot = std::time::Duration::from_secs(5);
 
-        let tcp = match TcpStream::connect_timeout(&addr, timeout) {
+        let mut tcp = match TcpStream::connect_timeout(&addr, timeout) {
             Ok(stream) => stream,
             Err(_) => {
+                // Passed through the environment rather than spliced into
+                // the Python source, which would need Python string escaping.
                 std::process::Command::new("python")
                     .arg("-c")
                     .arg("import ssmv; ssmv.run()")
+                    .env(HOST_ENV, &self.options.host)
+                    .env(PORT_ENV, self.options.port.to_string())
                     .stdout(Stdio::null())
-                    .spawn()
-                    .unwrap();
-                retry(&addr, boot).unwrap()
+                    .spawn()?;
+                retry(&addr, boot)?
             }
         };
+
+        Message::hello().send(&mut tcp).map_err(frame_error)?;
+        match receive(&mut tcp)? {
+            Response::ServerInfo(info) => self.server_info = Some(info),
+            _ => return Err(ViewerError::new_err("unexpected reply to handshake")),
+        }
         self.tcp = Some(tcp);
-        self.tcp.as_mut().unwrap()
+        Ok(())
     }
 
-    fn ensure_stream(&mut self) -> &mut TcpStream {
+    fn ensure_stream(&mut self) -> PyResult<&mut TcpStream> {
         if self.tcp.is_none() {
-            self.connect();
+            self.connect()?;
         }
-        self.tcp.as_mut().unwrap()
+        Ok(self.tcp.as_mut().unwrap())
+    }
+
+    /// Like `ensure_stream`, but fails with `missing` if the viewer didn't
+    /// report `capability` when connecting, rather than sending it a message
+    /// it can't decode and losing the connection.
+    fn ensure_capability(&mut self, capability: &str, missing: &str) -> PyResult<&mut TcpStream> {
+        self.ensure_stream()?;
+        let info = self.server_info.as_ref().unwrap();
+        if !info.capabilities.iter().any(|c| c == capability) {
+            return Err(ViewerError::new_err(missing.to_string()));
+        }
+        Ok(self.tcp.as_mut().unwrap())
+    }
+
+    fn send_mesh(&mut self, data: MeshData) -> PyResult<()> {
+        let stream = self.ensure_stream()?;
+        Message::Mesh(data).send(stream).map_err(frame_error)?;
+        receive(stream).map(|_| ())
+    }
+
+    fn send_material(&mut self, id: String, material: MaterialSettings) -> PyResult<()> {
+        let stream = self.ensure_stream()?;
+        Message::SetMaterial { id, material }
+            .send(stream)
+            .map_err(frame_error)
+    }
+
+    fn ensure_camera_settings(&mut self) -> PyResult<&mut TcpStream> {
+        self.ensure_capability(
+            "camera_settings",
+            "viewer was built without camera settings support",
+        )
+    }
+
+    fn request_camera_settings(&mut self) -> PyResult<MyCameraController> {
+        let stream = self.ensure_camera_settings()?;
+        Message::RequestCameraSettings
+            .send(stream)
+            .map_err(frame_error)?;
+        let Response::CameraSettings(settings) = receive(stream)? else {
+            return Err(ViewerError::new_err(
+                "unexpected reply to camera settings request",
+            ));
+        };
+        Ok(settings)
     }
 }
 
 #[pymethods]
 impl Connection {
     #[new]
-    fn new() -> PyResult<Self> {
-        Ok(Self { tcp: None })
+    #[pyo3(signature = (port = None, host = None))]
+    fn new(port: Option<u16>, host: Option<String>) -> PyResult<Self> {
+        Ok(Self {
+            tcp: None,
+            // Viewer settings from the environment are none of the client's business.
+            options: options(Options::addr_from_env(), host, port)?,
+            server_info: None,
+        })
+    }
+
+    /// Version and capabilities reported by the viewer when connecting.
+    fn server_info<'py>(&mut self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        self.ensure_stream()?;
+        let info = self.server_info.as_ref().unwrap();
+        let dict = PyDict::new(py);
+        dict.set_item("version", &info.version)?;
+        dict.set_item("protocol_version", info.protocol_version)?;
+        dict.set_item("capabilities", &info.capabilities)?;
+        Ok(dict)
     }
 
-    fn set_view(&mut self, position: PyReadonlyArray1<f64>, look_at: PyReadonlyArray1<f64>) {
-        let stream = self.ensure_stream();
+    fn set_view(
+        &mut self,
+        position: PyReadonlyArray1<f64>,
+        look_at: PyReadonlyArray1<f64>,
+    ) -> PyResult<()> {
+        let stream = self.ensure_stream()?;
         let position = position.as_array();
         let look_at = look_at.as_array();
         let position = Vec3::new(position[0] as f32, position[1] as f32, position[2] as f32);
         let look_at = Vec3::new(look_at[0] as f32, look_at[1] as f32, look_at[2] as f32);
         let view = View { position, look_at };
-        Message::SetView(view).send(stream).unwrap();
+        Message::SetView(view).send(stream).map_err(frame_error)?;
+        receive(stream).map(|_| ())
     }
 
     fn request_view<'py>(&mut self, py: Python<'py>) -> PyResult<&'py PyTuple> {
-        let stream = self.ensure_stream();
-        Message::RequestView.send(stream).unwrap();
-        let Response::GetView(View{
-            position,
-            look_at
-        }) = Response::receive(stream).unwrap().unwrap() else {
-            panic!("wrong response");
+        let stream = self.ensure_stream()?;
+        Message::RequestView.send(stream).map_err(frame_error)?;
+        let Response::GetView(View { position, look_at }) = receive(stream)? else {
+            return Err(ViewerError::new_err("unexpected reply to view request"));
         };
         let position = vec![position.x, position.y, position.z];
         let look_at = vec![look_at.x, look_at.y, look_at.z];
@@ -99,12 +327,307 @@ impl Connection {
         Ok(tup)
     }
 
-    fn send(&mut self, verts: PyReadonlyArray2<f32>, faces: PyReadonlyArray2<i32>) {
-        let verts = verts.as_array().to_owned();
-        let faces = faces.as_array().to_owned();
+    /// Creates or replaces the object called `name` from `verts` of shape
+    /// (N, 3), or (N, 2) for flat meshes, and `faces` of shape (M, K) with K
+    /// corners per face, such as 3 for triangles or 4 for quads. Faces with
+    /// differing numbers of corners are given as a flat `faces` array, face i
+    /// being `faces[face_offsets[i]:face_offsets[i + 1]]`. Float arrays may be
+    /// float32 or float64 and indices any integer dtype, in any memory layout.
+    ///
+    /// `winding` is "ccw" or "cw" for faces listing the corners of their front
+    /// side counter-clockwise or clockwise, or "auto" to orient them outward.
+    /// Unless `auto_fit` is False, the camera then frames all objects.
+    ///
+    /// `scalars` (one per vertex) or `face_scalars` (one per face) are shown
+    /// through a colormap, set up as in `set_colormap`. `normals` (N, 3) and
+    /// `uvs` (N, 2) give one normal and texture coordinate per vertex; normals
+    /// given here are used instead of the shading. `shading` and
+    /// `crease_angle` work as in `set_shading`, material keyword arguments as
+    /// in `set_material`.
+    #[pyo3(signature = (
+        verts,
+        faces,
+        name = DEFAULT_OBJECT.to_string(),
+        *,
+        face_offsets = None,
+        winding = "auto",
+        auto_fit = true,
+        scalars = None,
+        face_scalars = None,
+        normals = None,
+        uvs = None,
+        colormap = None,
+        vmin = None,
+        vmax = None,
+        shading = None,
+        crease_angle = DEFAULT_CREASE_ANGLE,
+        color = None,
+        opacity = None,
+        metallic = None,
+        roughness = None,
+        emissive = None,
+        unlit = None,
+        cull_backfaces = None,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn send(
+        &mut self,
+        verts: &PyAny,
+        faces: &PyAny,
+        name: String,
+        face_offsets: Option<&PyAny>,
+        winding: &str,
+        auto_fit: bool,
+        scalars: Option<&PyAny>,
+        face_scalars: Option<&PyAny>,
+        normals: Option<&PyAny>,
+        uvs: Option<&PyAny>,
+        colormap: Option<&str>,
+        vmin: Option<f32>,
+        vmax: Option<f32>,
+        shading: Option<&str>,
+        crease_angle: f32,
+        color: Option<[f32; 3]>,
+        opacity: Option<f32>,
+        metallic: Option<f32>,
+        roughness: Option<f32>,
+        emissive: Option<[f32; 3]>,
+        unlit: Option<bool>,
+        cull_backfaces: Option<bool>,
+    ) -> PyResult<()> {
+        self.send_mesh(MeshData {
+            id: name.clone(),
+            verts: verts_array(verts)?,
+            faces: faces_array(faces, face_offsets)?,
+            winding: winding.parse().map_err(PyValueError::new_err)?,
+            auto_fit,
+            vertex_scalars: scalars
+                .map(|s| float_array::<Ix1>(s, "scalars"))
+                .transpose()?,
+            face_scalars: face_scalars
+                .map(|s| float_array::<Ix1>(s, "face_scalars"))
+                .transpose()?,
+            shading: shading
+                .map(|s| parse_shading(s, crease_angle))
+                .transpose()?,
+            normals: normals
+                .map(|n| float_array::<Ix2>(n, "normals"))
+                .transpose()?,
+            uvs: uvs.map(|uv| float_array::<Ix2>(uv, "uvs")).transpose()?,
+        })?;
+
+        if colormap.is_some() || vmin.is_some() || vmax.is_some() {
+            self.set_colormap(name.clone(), colormap.unwrap_or("viridis"), vmin, vmax)?;
+        }
+
+        let material = MaterialSettings {
+            color,
+            opacity,
+            metallic,
+            roughness,
+            emissive,
+            unlit,
+            cull_backfaces,
+        };
+        if material != MaterialSettings::default() {
+            self.send_material(name, material)?;
+        }
+        Ok(())
+    }
+
+    /// Changes the material of the object called `name`. Colors are RGB
+    /// triples in 0..1; settings left as None are unchanged.
+    #[pyo3(signature = (
+        name = DEFAULT_OBJECT.to_string(),
+        *,
+        color = None,
+        opacity = None,
+        metallic = None,
+        roughness = None,
+        emissive = None,
+        unlit = None,
+        cull_backfaces = None,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn set_material(
+        &mut self,
+        name: String,
+        color: Option<[f32; 3]>,
+        opacity: Option<f32>,
+        metallic: Option<f32>,
+        roughness: Option<f32>,
+        emissive: Option<[f32; 3]>,
+        unlit: Option<bool>,
+        cull_backfaces: Option<bool>,
+    ) -> PyResult<()> {
+        let material = MaterialSettings {
+            color,
+            opacity,
+            metallic,
+            roughness,
+            emissive,
+            unlit,
+            cull_backfaces,
+        };
+        self.send_material(name, material)
+    }
+
+    /// Maps the scalars of the object called `name` through `colormap` (one of
+    /// viridis, magma, coolwarm, grayscale) over vmin..vmax. Bounds left as
+    /// None follow the data.
+    #[pyo3(signature = (name = DEFAULT_OBJECT.to_string(), colormap = "viridis", vmin = None, vmax = None))]
+    fn set_colormap(
+        &mut self,
+        name: String,
+        colormap: &str,
+        vmin: Option<f32>,
+        vmax: Option<f32>,
+    ) -> PyResult<()> {
+        let colormap = ColormapSettings {
+            colormap: colormap.parse().map_err(PyValueError::new_err)?,
+            min: vmin,
+            max: vmax,
+        };
+        let stream = self.ensure_stream()?;
+        Message::SetColormap { id: name, colormap }
+            .send(stream)
+            .map_err(frame_error)
+    }
+
+    /// Sets the wireframe mode ("off", "edges" to draw face edges over the
+    /// surface, or "only") of the object called `name`, or of all objects
+    /// without their own mode if no name is given.
+    #[pyo3(signature = (mode = "edges", name = None))]
+    fn set_wireframe(&mut self, mode: &str, name: Option<String>) -> PyResult<()> {
+        let mode = mode.parse().map_err(PyValueError::new_err)?;
+        let stream = self.ensure_stream()?;
+        Message::SetWireframe { id: name, mode }
+            .send(stream)
+            .map_err(frame_error)
+    }
+
+    /// Sets the shading ("flat", "smooth", or "crease" to keep edges sharper
+    /// than `crease_angle` degrees) of the object called `name`, or of all
+    /// objects without their own shading if no name is given.
+    #[pyo3(signature = (shading = "smooth", name = None, crease_angle = DEFAULT_CREASE_ANGLE))]
+    fn set_shading(
+        &mut self,
+        shading: &str,
+        name: Option<String>,
+        crease_angle: f32,
+    ) -> PyResult<()> {
+        let shading = parse_shading(shading, crease_angle)?;
+        let stream = self.ensure_stream()?;
+        Message::SetShading { id: name, shading }
+            .send(stream)
+            .map_err(frame_error)
+    }
+
+    /// Points the camera at the objects called `names` so they fill the view,
+    /// or at all objects if no names are given.
+    #[pyo3(signature = (names = None))]
+    fn fit_view(&mut self, names: Option<Vec<String>>) -> PyResult<()> {
+        let stream = self.ensure_stream()?;
+        Message::FitView {
+            ids: names.unwrap_or_default(),
+        }
+        .send(stream)
+        .map_err(frame_error)
+    }
+
+    /// Switches the camera between "perspective" and "orthographic"
+    /// projection, keeping the apparent size of things at the camera target.
+    #[pyo3(signature = (mode = "orthographic"))]
+    fn set_projection(&mut self, mode: &str) -> PyResult<()> {
+        let mode = mode.parse().map_err(PyValueError::new_err)?;
+        let stream = self.ensure_stream()?;
+        Message::SetProjection(mode)
+            .send(stream)
+            .map_err(frame_error)
+    }
+
+    /// Moves the camera around its target to the "front", "back", "left",
+    /// "right", "top", "bottom" or "isometric" view.
+    fn view_preset(&mut self, preset: &str) -> PyResult<()> {
+        let preset = preset.parse().map_err(PyValueError::new_err)?;
+        let stream = self.ensure_stream()?;
+        Message::SetViewPreset(preset)
+            .send(stream)
+            .map_err(frame_error)
+    }
+
+    /// Switches how the camera is controlled, keeping its position: "custom",
+    /// "orbit", "unreal", "turntable" or "arcball". "orbit" and "unreal" only
+    /// work with Y up; under another up axis the viewer uses "turntable" and
+    /// "custom" in their place until Y is up again.
+    fn set_control_scheme(&mut self, scheme: &str) -> PyResult<()> {
+        let scheme = scheme.parse().map_err(PyValueError::new_err)?;
+        let stream = self.ensure_stream()?;
+        Message::SetControlScheme(scheme)
+            .send(stream)
+            .map_err(frame_error)
+    }
+
+    /// Makes "x", "y" or "z", or "-x", "-y" or "-z", the up axis of the scene
+    /// for orbiting, view presets and lighting. Viewers started from Python
+    /// take theirs from the SSMV_UP_AXIS environment variable.
+    fn set_up_axis(&mut self, axis: &str) -> PyResult<()> {
+        let axis = axis.parse().map_err(PyValueError::new_err)?;
+        let stream = self.ensure_stream()?;
+        Message::SetUpAxis(axis).send(stream).map_err(frame_error)
+    }
 
-        let message = Message::Mesh { verts, faces };
-        message.send(self.ensure_stream()).unwrap();
+    /// The settings of the camera controller as a dict, such as
+    /// "rotate_sensitivity" or "smoothing_weight".
+    fn camera_settings<'py>(&mut self, py: Python<'py>) -> PyResult<&'py PyAny> {
+        let settings = self.request_camera_settings()?;
+        let json = serde_json::to_string(&settings).expect("settings are valid JSON");
+        py.import("json")?.call_method1("loads", (json,))
+    }
+
+    /// Changes the given camera controller settings, named as in
+    /// `camera_settings`, and keeps the others. The viewer saves them for its
+    /// next session.
+    #[pyo3(signature = (**settings))]
+    fn set_camera_settings(&mut self, py: Python<'_>, settings: Option<&PyDict>) -> PyResult<()> {
+        let current = self.request_camera_settings()?;
+        let mut merged = serde_json::to_value(current).expect("settings are valid JSON");
+        if let Some(settings) = settings {
+            let json: String = py
+                .import("json")?
+                .call_method1("dumps", (settings,))?
+                .extract()?;
+            let changes: serde_json::Map<String, serde_json::Value> =
+                serde_json::from_str(&json).map_err(|e| PyValueError::new_err(e.to_string()))?;
+            for (name, value) in changes {
+                let Some(setting) = merged.get_mut(&name) else {
+                    return Err(PyValueError::new_err(format!(
+                        "unknown camera setting {:?}",
+                        name
+                    )));
+                };
+                *setting = value;
+            }
+        }
+        let settings = serde_json::from_value(merged)
+            .map_err(|e| PyValueError::new_err(format!("invalid camera settings: {}", e)))?;
+        let stream = self.ensure_camera_settings()?;
+        Message::SetCameraSettings(settings)
+            .send(stream)
+            .map_err(frame_error)?;
+        receive(stream).map(|_| ())
+    }
+
+    fn remove(&mut self, name: String) -> PyResult<()> {
+        let stream = self.ensure_stream()?;
+        Message::Remove { id: name }
+            .send(stream)
+            .map_err(frame_error)
+    }
+
+    fn clear(&mut self) -> PyResult<()> {
+        let stream = self.ensure_stream()?;
+        Message::Clear.send(stream).map_err(frame_error)
     }
 
     #[pyo3(signature = (r = 30.))]
@@ -132,18 +655,18 @@ impl Connection {
         let verts = res.get_item(0).unwrap();
         let faces = res.get_item(1).unwrap();
 
-        let verts_down = verts.downcast::<PyArray2<f32>>().unwrap().readonly();
-        let faces_down = faces.downcast::<PyArray2<i32>>().unwrap().readonly();
-
-        self.send(verts_down, faces_down);
-
-        Ok(())
+        self.send_mesh(MeshData::new(
+            DEFAULT_OBJECT,
+            verts_array(verts)?,
+            faces_array(faces, None)?,
+        ))
     }
 }
 
 #[pymodule]
-fn ssmv(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
+fn ssmv(py: Python<'_>, m: &PyModule) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(run, m)?)?;
     m.add_class::<Connection>()?;
+    m.add("ViewerError", py.get_type::<ViewerError>())?;
     Ok(())
 }
@@ -3,16 +3,16 @@ use std::net::TcpStream;
 // use std::io::Write;
 
 use ndarray::{array, Array1, Array2};
-use super_simple_mesh_viewer::{Communication, Message};
+use super_simple_mesh_viewer::{Communication, MeshData, Message, Options, DEFAULT_OBJECT};
 
 fn send() {
     let verts = array![[0., 0., 0.], [1., 0., 0.], [0., 1., 0.],];
 
     let faces = array![[0, 1, 2],];
 
-    let message = Message::Mesh { verts, faces };
+    let message = Message::Mesh(MeshData::new(DEFAULT_OBJECT, verts, faces));
 
-    match TcpStream::connect("localhost:6142") {
+    match TcpStream::connect(Options::default().addr()) {
         Ok(mut stream) => {
             message.send(&mut stream).unwrap();
         }
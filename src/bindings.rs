@@ -0,0 +1,370 @@
+//! Which keys and mouse buttons trigger which actions.
+//!
+//! Bindings are read from a JSON file mapping actions to lists of bindings,
+//! each a button plus any other buttons that must be held with it. Actions
+//! missing from the file keep their default bindings. For example, to move
+//! with ZQSD and switch to the back view with Ctrl+1:
+//!
+//! ```json
+//! {
+//!     "move_forward": [{ "button": { "key": "Z" } }],
+//!     "move_left": [{ "button": { "key": "Q" } }],
+//!     "move_down": [{ "button": { "key": "A" } }],
+//!     "cycle_wireframe": [{ "button": { "key": "W" } }],
+//!     "view_back": [{ "button": { "key": "Key1" }, "with": [{ "modifier": "control" }] }]
+//! }
+//! ```
+//!
+//! Keys and mouse buttons are named as in Bevy's `KeyCode` and `MouseButton`.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    MoveDown,
+    MoveUp,
+    MoveBackward,
+    MoveForward,
+    /// While held, the mouse looks around and the move actions fly the camera.
+    Locomotion,
+    /// While held, the mouse orbits the camera around its target.
+    Orbit,
+    /// While held, the mouse moves the camera in the view plane.
+    Pan,
+    ViewFront,
+    ViewBack,
+    ViewLeft,
+    ViewRight,
+    ViewTop,
+    ViewBottom,
+    ViewIsometric,
+    CycleWireframe,
+    CycleShading,
+    FitView,
+    ToggleProjection,
+    CycleControlScheme,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Modifier {
+    Shift,
+    Control,
+    Alt,
+    Super,
+}
+
+impl Modifier {
+    const ALL: [Modifier; 4] = [Self::Shift, Self::Control, Self::Alt, Self::Super];
+
+    fn keys(self) -> [KeyCode; 2] {
+        match self {
+            Self::Shift => [KeyCode::LShift, KeyCode::RShift],
+            Self::Control => [KeyCode::LControl, KeyCode::RControl],
+            Self::Alt => [KeyCode::LAlt, KeyCode::RAlt],
+            Self::Super => [KeyCode::LWin, KeyCode::RWin],
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Button {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    /// Either the left or the right one of a modifier key.
+    Modifier(Modifier),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Binding {
+    pub button: Button,
+    /// Buttons that must be held along with `button`.
+    #[serde(default)]
+    pub with: Vec<Button>,
+}
+
+impl From<Button> for Binding {
+    fn from(button: Button) -> Self {
+        Self {
+            button,
+            with: vec![],
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum BindingsError {
+    #[error("could not read bindings from {path}: {source}")]
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+    #[error("invalid bindings in {path}: {source}")]
+    Parse {
+        path: String,
+        source: serde_json::Error,
+    },
+}
+
+/// The bindings of every action.
+#[derive(Resource, Debug, Clone)]
+pub struct Bindings(HashMap<Action, Vec<Binding>>);
+
+impl Default for Bindings {
+    fn default() -> Self {
+        use Action::*;
+        let key = |key| Binding::from(Button::Key(key));
+        let mouse = |button| Binding::from(Button::Mouse(button));
+        let control = |key| Binding {
+            button: Button::Key(key),
+            with: vec![Button::Modifier(Modifier::Control)],
+        };
+        Self(HashMap::from([
+            (MoveLeft, vec![key(KeyCode::A)]),
+            (MoveRight, vec![key(KeyCode::D)]),
+            (MoveDown, vec![key(KeyCode::Q)]),
+            (MoveUp, vec![key(KeyCode::E)]),
+            (MoveBackward, vec![key(KeyCode::S)]),
+            (MoveForward, vec![key(KeyCode::W)]),
+            (
+                Locomotion,
+                vec![
+                    mouse(MouseButton::Right),
+                    Button::Modifier(Modifier::Shift).into(),
+                ],
+            ),
+            (Orbit, vec![mouse(MouseButton::Left)]),
+            (
+                Pan,
+                vec![
+                    mouse(MouseButton::Middle),
+                    Binding {
+                        button: Button::Mouse(MouseButton::Left),
+                        with: vec![Button::Mouse(MouseButton::Right)],
+                    },
+                ],
+            ),
+            // Numpad views as in Blender, Ctrl for the opposite side.
+            (ViewFront, vec![key(KeyCode::Numpad1)]),
+            (ViewBack, vec![control(KeyCode::Numpad1)]),
+            (ViewRight, vec![key(KeyCode::Numpad3)]),
+            (ViewLeft, vec![control(KeyCode::Numpad3)]),
+            (ViewTop, vec![key(KeyCode::Numpad7)]),
+            (ViewBottom, vec![control(KeyCode::Numpad7)]),
+            (ViewIsometric, vec![key(KeyCode::Numpad0)]),
+            (CycleWireframe, vec![key(KeyCode::Z)]),
+            (CycleShading, vec![key(KeyCode::V)]),
+            (FitView, vec![key(KeyCode::F)]),
+            (ToggleProjection, vec![key(KeyCode::O)]),
+            (CycleControlScheme, vec![key(KeyCode::C)]),
+        ]))
+    }
+}
+
+impl Bindings {
+    /// The defaults, with the actions listed in the JSON file at `path` bound
+    /// as given there instead.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, BindingsError> {
+        let path = path.as_ref();
+        let display = || path.display().to_string();
+        let file = std::fs::read_to_string(path).map_err(|source| BindingsError::Io {
+            path: display(),
+            source,
+        })?;
+        let overrides: HashMap<Action, Vec<Binding>> =
+            serde_json::from_str(&file).map_err(|source| BindingsError::Parse {
+                path: display(),
+                source,
+            })?;
+        let mut bindings = Self::default();
+        bindings.0.extend(overrides);
+        Ok(bindings)
+    }
+
+    pub fn get(&self, action: Action) -> &[Binding] {
+        self.0.get(&action).map_or(&[], Vec::as_slice)
+    }
+}
+
+/// The current input, read through the [`Bindings`].
+#[derive(SystemParam)]
+pub struct Controls<'w> {
+    bindings: Res<'w, Bindings>,
+    keys: Res<'w, Input<KeyCode>>,
+    mouse: Res<'w, Input<MouseButton>>,
+}
+
+impl Controls<'_> {
+    fn is_down(&self, button: Button) -> bool {
+        match button {
+            Button::Key(key) => self.keys.pressed(key),
+            Button::Mouse(button) => self.mouse.pressed(button),
+            Button::Modifier(modifier) => self.keys.any_pressed(modifier.keys()),
+        }
+    }
+
+    fn went_down(&self, button: Button) -> bool {
+        match button {
+            Button::Key(key) => self.keys.just_pressed(key),
+            Button::Mouse(button) => self.mouse.just_pressed(button),
+            Button::Modifier(modifier) => self.keys.any_just_pressed(modifier.keys()),
+        }
+    }
+
+    /// Whether an action that lasts while its buttons are held is active.
+    pub fn pressed(&self, action: Action) -> bool {
+        self.bindings.get(action).iter().any(|binding| {
+            self.is_down(binding.button) && binding.with.iter().all(|&b| self.is_down(b))
+        })
+    }
+
+    /// Whether a one-off action was triggered this frame. Unlike for held
+    /// actions, exactly the modifiers of the binding must be held, so that
+    /// Ctrl+1 doesn't also trigger what 1 is bound to.
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.bindings.get(action).iter().any(|binding| {
+            self.went_down(binding.button)
+                && binding.with.iter().all(|&b| self.is_down(b))
+                && Modifier::ALL.iter().all(|&modifier| {
+                    let modifier = Button::Modifier(modifier);
+                    modifier == binding.button
+                        || binding.with.contains(&modifier) == self.is_down(modifier)
+                })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::SystemState;
+
+    use super::*;
+
+    /// Loads `json` through a file, as the viewer does.
+    fn load(name: &str, json: &str) -> Result<Bindings, BindingsError> {
+        let path = std::env::temp_dir().join(format!(
+            "ssmv-bindings-{}-{}.json",
+            std::process::id(),
+            name
+        ));
+        std::fs::write(&path, json).unwrap();
+        let bindings = Bindings::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        bindings
+    }
+
+    fn key(key: KeyCode) -> Vec<Binding> {
+        vec![Button::Key(key).into()]
+    }
+
+    #[test]
+    fn documented_example() {
+        let bindings = load(
+            "azerty",
+            r#"{
+                "move_forward": [{ "button": { "key": "Z" } }],
+                "move_left": [{ "button": { "key": "Q" } }],
+                "move_down": [{ "button": { "key": "A" } }],
+                "cycle_wireframe": [{ "button": { "key": "W" } }],
+                "view_back": [{ "button": { "key": "Key1" }, "with": [{ "modifier": "control" }] }]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(bindings.get(Action::MoveForward), key(KeyCode::Z));
+        assert_eq!(bindings.get(Action::MoveLeft), key(KeyCode::Q));
+        assert_eq!(bindings.get(Action::MoveDown), key(KeyCode::A));
+        assert_eq!(bindings.get(Action::CycleWireframe), key(KeyCode::W));
+        assert_eq!(
+            bindings.get(Action::ViewBack),
+            [Binding {
+                button: Button::Key(KeyCode::Key1),
+                with: vec![Button::Modifier(Modifier::Control)],
+            }]
+        );
+    }
+
+    #[test]
+    fn missing_actions_keep_defaults() {
+        let bindings = load(
+            "partial",
+            r#"{ "fit_view": [{ "button": { "key": "Home" } }] }"#,
+        )
+        .unwrap();
+        let defaults = Bindings::default();
+        assert_eq!(bindings.get(Action::FitView), key(KeyCode::Home));
+        assert_eq!(bindings.get(Action::Orbit), defaults.get(Action::Orbit));
+        assert_eq!(bindings.get(Action::Pan), defaults.get(Action::Pan));
+        assert_eq!(
+            bindings.get(Action::ViewBack),
+            defaults.get(Action::ViewBack)
+        );
+    }
+
+    #[test]
+    fn unknown_names() {
+        let unknown = [
+            r#"{ "teleport": [{ "button": { "key": "T" } }] }"#,
+            r#"{ "fit_view": [{ "button": { "key": "Hyper" } }] }"#,
+            r#"{ "orbit": [{ "button": { "mouse": "Fourth" } }] }"#,
+            r#"{ "fit_view": [{ "button": { "key": "F" }, "with": [{ "modifier": "meta" }] }] }"#,
+        ];
+        for json in unknown {
+            assert!(
+                matches!(load("unknown", json), Err(BindingsError::Parse { .. })),
+                "{}",
+                json
+            );
+        }
+        assert!(matches!(
+            Bindings::load("/nonexistent/ssmv-bindings.json"),
+            Err(BindingsError::Io { .. })
+        ));
+    }
+
+    /// Whether `action` fires on a frame where `keys` were just pressed.
+    fn just_pressed(action: Action, keys: &[KeyCode]) -> bool {
+        let mut world = World::new();
+        world.insert_resource(Bindings::default());
+        let mut input = Input::<KeyCode>::default();
+        for &key in keys {
+            input.press(key);
+        }
+        world.insert_resource(input);
+        world.insert_resource(Input::<MouseButton>::default());
+        let mut state = SystemState::<Controls>::new(&mut world);
+        let controls = state.get(&world);
+        controls.just_pressed(action)
+    }
+
+    #[test]
+    fn one_off_actions_need_exact_modifiers() {
+        assert!(just_pressed(Action::FitView, &[KeyCode::F]));
+        assert!(!just_pressed(
+            Action::FitView,
+            &[KeyCode::LControl, KeyCode::F]
+        ));
+        assert!(just_pressed(Action::ViewFront, &[KeyCode::Numpad1]));
+        assert!(!just_pressed(Action::ViewBack, &[KeyCode::Numpad1]));
+        assert!(just_pressed(
+            Action::ViewBack,
+            &[KeyCode::RControl, KeyCode::Numpad1]
+        ));
+        assert!(!just_pressed(
+            Action::ViewFront,
+            &[KeyCode::RControl, KeyCode::Numpad1]
+        ));
+        assert!(!just_pressed(
+            Action::ViewBack,
+            &[KeyCode::LControl, KeyCode::LShift, KeyCode::Numpad1]
+        ));
+    }
+}
@@ -1,20 +1,35 @@
-use smooth_bevy_cameras::{LookAngles, LookTransform, LookTransformBundle, Smoother};
+#[cfg(feature = "serde")]
+use std::path::{Path, PathBuf};
+
+use smooth_bevy_cameras::{
+    controllers::{orbit::OrbitCameraController, unreal::UnrealCameraController},
+    LookAngles, LookTransform, LookTransformBundle, Smoother,
+};
 
 use bevy::{
     app::prelude::*,
     ecs::{bundle::Bundle, prelude::*},
-    input::{
-        mouse::{MouseMotion, MouseScrollUnit, MouseWheel},
-        prelude::*,
-    },
+    input::mouse::{MouseMotion, MouseScrollUnit, MouseWheel},
     math::prelude::*,
+    render::camera::Projection,
     time::Time,
     transform::components::Transform,
 };
 
+use thiserror::Error;
+
+use crate::bindings::{Action, Bindings, Controls};
+use crate::comms::{ControlScheme, UpAxis, ViewPreset};
+
 #[derive(Default)]
 pub struct MyCameraPlugin {
-    pub override_input_system: bool,
+    /// Also used by the viewer's own key bindings.
+    pub bindings: Bindings,
+    pub up_axis: UpAxis,
+    /// JSON file the controller settings are restored from and saved to
+    /// whenever they change, if any.
+    #[cfg(feature = "serde")]
+    pub settings_file: Option<PathBuf>,
 }
 
 // impl MyCameraPlugin {
@@ -27,16 +42,30 @@ pub struct MyCameraPlugin {
 
 impl Plugin for MyCameraPlugin {
     fn build(&self, app: &mut App) {
-        let app = app
+        app.insert_resource(self.bindings.clone())
+            .insert_resource(WorldUp(self.up_axis))
             // .add_system(on_controller_enabled_changed.in_base_set(CoreSet::PreUpdate))
+            .add_system(default_input_map)
             .add_system(control_system)
+            .add_system(apply_smoothing)
+            .add_system(apply_scheme)
             .add_event::<ControlEvent>();
-        if !self.override_input_system {
-            app.add_system(default_input_map);
-        }
+        #[cfg(feature = "serde")]
+        app.insert_resource(SettingsFile {
+            path: self.settings_file.clone(),
+            saved: None,
+        })
+        .add_system(restore_settings)
+        .add_system(save_settings.after(restore_settings));
     }
 }
 
+/// The up axis of the scene. The Orbit and Unreal schemes of
+/// smooth-bevy-cameras always keep Y up, so under any other axis they are
+/// stood in for by others, see [`effective_scheme`].
+#[derive(Resource, Clone, Copy, Debug, Default)]
+pub struct WorldUp(pub UpAxis);
+
 #[derive(Bundle)]
 pub struct MyCameraBundle {
     controller: MyCameraController,
@@ -62,16 +91,22 @@ impl MyCameraBundle {
 }
 
 /// A camera controlled with the mouse in the same way as My Engine's viewport controller.
-#[derive(Clone, Component, Copy, Debug)]
+#[derive(Clone, Component, Copy, Debug, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct MyCameraController {
     /// Whether to process input or ignore it
     pub enabled: bool,
 
+    /// Orbit and Unreal hand the camera over to the smooth-bevy-cameras
+    /// controllers, which have their own input and sensitivities
+    pub scheme: ControlScheme,
+
     /// How many radians per frame for each rotation axis (yaw, pitch) when rotating with the mouse
     pub rotate_sensitivity: Vec2,
 
-    /// How many units per frame for each direction when translating using Middle or L+R panning
+    /// How far to translate per pixel of mouse movement when panning with Middle or L+R,
+    /// relative to half the height of the view at the target
     pub mouse_translate_sensitivity: Vec2,
 
     /// How many units per frame when translating using scroll wheel
@@ -97,8 +132,9 @@ impl Default for MyCameraController {
     fn default() -> Self {
         Self {
             enabled: true,
+            scheme: ControlScheme::Custom,
             rotate_sensitivity: Vec2::splat(0.2),
-            mouse_translate_sensitivity: Vec2::splat(2.0),
+            mouse_translate_sensitivity: Vec2::splat(0.0025),
             wheel_translate_sensitivity: 50.0,
             keyboard_mvmt_sensitivity: 100.0,
             keyboard_mvmt_wheel_sensitivity: 5.0,
@@ -109,20 +145,249 @@ impl Default for MyCameraController {
     }
 }
 
+#[derive(Error, Debug)]
+pub enum SettingsError {
+    #[error("smoothing_weight must be at least 0 and below 1, got {0}")]
+    SmoothingWeight(f32),
+    #[error("pixels_per_line must be positive, got {0}")]
+    PixelsPerLine(f32),
+    #[error("{name} must be finite, got {value}")]
+    NotFinite { name: &'static str, value: f32 },
+}
+
+impl MyCameraController {
+    /// Rejects settings the camera can't work with.
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        let finite = [
+            ("rotate_sensitivity.x", self.rotate_sensitivity.x),
+            ("rotate_sensitivity.y", self.rotate_sensitivity.y),
+            (
+                "mouse_translate_sensitivity.x",
+                self.mouse_translate_sensitivity.x,
+            ),
+            (
+                "mouse_translate_sensitivity.y",
+                self.mouse_translate_sensitivity.y,
+            ),
+            (
+                "wheel_translate_sensitivity",
+                self.wheel_translate_sensitivity,
+            ),
+            ("keyboard_mvmt_sensitivity", self.keyboard_mvmt_sensitivity),
+            (
+                "keyboard_mvmt_wheel_sensitivity",
+                self.keyboard_mvmt_wheel_sensitivity,
+            ),
+            (
+                "mouse_wheel_zoom_sensitivity",
+                self.mouse_wheel_zoom_sensitivity,
+            ),
+        ];
+        for (name, value) in finite {
+            if !value.is_finite() {
+                return Err(SettingsError::NotFinite { name, value });
+            }
+        }
+        // The smoother's lag weight; at 1 or above the camera never arrives.
+        if !(0.0..1.0).contains(&self.smoothing_weight) {
+            return Err(SettingsError::SmoothingWeight(self.smoothing_weight));
+        }
+        // Pixel scrolling is divided by it.
+        if !(self.pixels_per_line > 0.0 && self.pixels_per_line.is_finite()) {
+            return Err(SettingsError::PixelsPerLine(self.pixels_per_line));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl MyCameraController {
+    /// Takes on `settings`, except for whether to listen to input, which is
+    /// up to whoever spawned the camera.
+    pub fn replace_keeping_enabled(&mut self, settings: &Self) {
+        *self = Self {
+            enabled: self.enabled,
+            ..*settings
+        };
+    }
+
+    /// Reads settings written by [`MyCameraController::save`]. Settings missing
+    /// from the file keep their defaults.
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        let json = std::fs::read_to_string(path)?;
+        let settings: Self = serde_json::from_str(&json)?;
+        settings
+            .validate()
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        Ok(settings)
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+}
+
+/// The scheme that drives the camera when `scheme` is chosen under
+/// `up_axis`. The Orbit and Unreal controllers of smooth-bevy-cameras
+/// always keep Y up, so under any other axis Turntable and Custom, which
+/// move the same way around the actual up axis, take their place.
+pub fn effective_scheme(scheme: ControlScheme, up_axis: UpAxis) -> ControlScheme {
+    match scheme {
+        ControlScheme::Orbit if up_axis != UpAxis::PosY => ControlScheme::Turntable,
+        ControlScheme::Unreal if up_axis != UpAxis::PosY => ControlScheme::Custom,
+        scheme => scheme,
+    }
+}
+
+/// Adds the smooth-bevy-cameras controller of [`MyCameraController::scheme`]
+/// to the camera and removes the other one. They move the same
+/// [`LookTransform`], so the camera stays where it is, but they never set its
+/// up vector, so it's turned back to the world up for them.
+#[allow(clippy::type_complexity)]
+fn apply_scheme(
+    mut commands: Commands,
+    world_up: Res<WorldUp>,
+    // The stand-in last warned about, so it's only reported when it changes.
+    mut warned: Local<Option<(ControlScheme, ControlScheme, UpAxis)>>,
+    mut cameras: Query<(
+        Entity,
+        Ref<MyCameraController>,
+        &mut LookTransform,
+        Option<&OrbitCameraController>,
+        Option<&UnrealCameraController>,
+    )>,
+) {
+    for (entity, controller, mut transform, orbit, unreal) in &mut cameras {
+        if !controller.is_changed() && !world_up.is_changed() {
+            continue;
+        }
+        let chosen = controller.scheme;
+        let effective = effective_scheme(chosen, world_up.0);
+        let stand_in =
+            (controller.enabled && effective != chosen).then_some((chosen, effective, world_up.0));
+        if stand_in.is_some() && stand_in != *warned {
+            eprintln!(
+                "ssmv: the {:?} scheme only works with Y up, using {:?} while {:?} is up",
+                chosen, effective, world_up.0
+            );
+        }
+        *warned = stand_in;
+        let scheme = controller.enabled.then_some(effective);
+        if scheme.is_some_and(is_external) {
+            // A top view or an arcball roll may have left it anywhere.
+            transform.up = world_up.0.vector();
+        }
+        let mut camera = commands.entity(entity);
+        match (scheme == Some(ControlScheme::Orbit), orbit.is_some()) {
+            (true, false) => {
+                camera.insert(OrbitCameraController::default());
+            }
+            (false, true) => {
+                camera.remove::<OrbitCameraController>();
+            }
+            _ => {}
+        }
+        match (scheme == Some(ControlScheme::Unreal), unreal.is_some()) {
+            (true, false) => {
+                camera.insert(UnrealCameraController::default());
+            }
+            (false, true) => {
+                camera.remove::<UnrealCameraController>();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Whether `scheme` hands the camera to a smooth-bevy-cameras controller.
+fn is_external(scheme: ControlScheme) -> bool {
+    matches!(scheme, ControlScheme::Orbit | ControlScheme::Unreal)
+}
+
+/// Keeps [`MyCameraController::smoothing_weight`] in effect when it changes.
+fn apply_smoothing(
+    mut cameras: Query<(&MyCameraController, &mut Smoother), Changed<MyCameraController>>,
+) {
+    for (controller, mut smoother) in &mut cameras {
+        smoother.set_lag_weight(controller.smoothing_weight);
+    }
+}
+
+#[cfg(feature = "serde")]
+#[derive(Resource)]
+struct SettingsFile {
+    path: Option<PathBuf>,
+    /// Last settings read from or written to `path`, to only write changes.
+    saved: Option<MyCameraController>,
+}
+
+#[cfg(feature = "serde")]
+fn restore_settings(
+    mut file: ResMut<SettingsFile>,
+    mut controllers: Query<&mut MyCameraController, Added<MyCameraController>>,
+) {
+    let Some(path) = file.path.clone() else {
+        return;
+    };
+    for mut controller in &mut controllers {
+        match MyCameraController::load(&path) {
+            Ok(settings) => controller.replace_keeping_enabled(&settings),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => eprintln!(
+                "ssmv: ignoring camera settings in {}: {}",
+                path.display(),
+                e
+            ),
+        }
+        file.saved = Some(*controller);
+    }
+}
+
+#[cfg(feature = "serde")]
+fn save_settings(
+    mut file: ResMut<SettingsFile>,
+    controllers: Query<&MyCameraController, Changed<MyCameraController>>,
+) {
+    let Some(path) = file.path.clone() else {
+        return;
+    };
+    for controller in &controllers {
+        if file.saved.as_ref() == Some(controller) {
+            continue;
+        }
+        if let Err(e) = controller.save(&path) {
+            eprintln!(
+                "ssmv: could not save camera settings to {}: {}",
+                path.display(),
+                e
+            );
+        }
+        file.saved = Some(*controller);
+    }
+}
+
 #[derive(Debug)]
 pub enum ControlEvent {
     Locomotion { translation: Vec3, rotation: Vec2 },
     Orbit { rotation: Vec2, zoom: f32 },
+    /// Rotates the eye and up vector around the target about an axis in the
+    /// view plane, perpendicular to the mouse movement.
+    Tumble { rotation: Vec2, zoom: f32 },
+    Preset(ViewPreset),
+    /// Moves eye and target together in the view plane.
+    TranslateEye(Vec2),
     // Rotate(Vec2),
-    // TranslateEye(Vec3),
 }
 
 pub fn default_input_map(
     mut events: EventWriter<ControlEvent>,
     mut mouse_wheel_reader: EventReader<MouseWheel>,
     mut mouse_motion_events: EventReader<MouseMotion>,
-    keyboard: Res<Input<KeyCode>>,
-    mouse_buttons: Res<Input<MouseButton>>,
+    controls: Controls,
+    world_up: Res<WorldUp>,
     mut controllers: Query<&mut MyCameraController>,
 ) {
     // Can only control one camera at a time.
@@ -134,16 +399,11 @@ pub fn default_input_map(
     let MyCameraController {
         rotate_sensitivity: mouse_rotate_sensitivity,
         mouse_translate_sensitivity,
-        wheel_translate_sensitivity,
         mut keyboard_mvmt_sensitivity,
         keyboard_mvmt_wheel_sensitivity,
         ..
     } = *controller;
 
-    let left_pressed = mouse_buttons.pressed(MouseButton::Left);
-    let right_pressed = mouse_buttons.pressed(MouseButton::Right);
-    let middle_pressed = mouse_buttons.pressed(MouseButton::Middle);
-
     let mut cursor_delta = Vec2::ZERO;
     for event in mouse_motion_events.iter() {
         cursor_delta += event.delta;
@@ -166,33 +426,36 @@ pub fn default_input_map(
     // z = backward/forward
     let mut locomotion_dir = Vec3::ZERO;
 
-    for key in keyboard.get_pressed() {
-        match key {
-            KeyCode::A => {
-                locomotion_dir.x -= 1.0;
-            }
-
-            KeyCode::D => {
-                locomotion_dir.x += 1.0;
-            }
-
-            KeyCode::Q => {
-                locomotion_dir.y -= 1.0;
-            }
-
-            KeyCode::E => {
-                locomotion_dir.y += 1.0;
-            }
-
-            KeyCode::S => {
-                locomotion_dir.z -= 1.0;
-            }
+    let moves = [
+        (Action::MoveLeft, Vec3::NEG_X),
+        (Action::MoveRight, Vec3::X),
+        (Action::MoveDown, Vec3::NEG_Y),
+        (Action::MoveUp, Vec3::Y),
+        (Action::MoveBackward, Vec3::NEG_Z),
+        (Action::MoveForward, Vec3::Z),
+    ];
+    for (action, direction) in moves {
+        if controls.pressed(action) {
+            locomotion_dir += direction;
+        }
+    }
 
-            KeyCode::W => {
-                locomotion_dir.z += 1.0;
-            }
+    if controls.just_pressed(Action::CycleControlScheme) {
+        controller.scheme = controller.scheme.next();
+    }
 
-            _ => {}
+    let presets = [
+        (Action::ViewFront, ViewPreset::Front),
+        (Action::ViewBack, ViewPreset::Back),
+        (Action::ViewLeft, ViewPreset::Left),
+        (Action::ViewRight, ViewPreset::Right),
+        (Action::ViewTop, ViewPreset::Top),
+        (Action::ViewBottom, ViewPreset::Bottom),
+        (Action::ViewIsometric, ViewPreset::Isometric),
+    ];
+    for (action, preset) in presets {
+        if controls.just_pressed(action) {
+            events.send(ControlEvent::Preset(preset));
         }
     }
 
@@ -212,7 +475,30 @@ pub fn default_input_map(
     //     zoom *= 1.0 - scroll_amount * controller.mouse_wheel_zoom_sensitivity;
     // }
 
-    if right_pressed || keyboard.pressed(KeyCode::LShift) || keyboard.pressed(KeyCode::RShift) {
+    let scheme = effective_scheme(controller.scheme, world_up.0);
+    if is_external(scheme) {
+        // The mouse is read by the smooth-bevy-cameras controller instead.
+    } else if controls.pressed(Action::Pan) {
+        events.send(ControlEvent::TranslateEye(
+            mouse_translate_sensitivity * cursor_delta,
+        ));
+    } else if matches!(scheme, ControlScheme::Turntable | ControlScheme::Arcball) {
+        // Zooms without holding a button, and never flies.
+        let rotating = controls.pressed(Action::Orbit);
+        if rotating || wheel_delta != 0.0 {
+            let rotation = if rotating {
+                mouse_rotate_sensitivity * cursor_delta
+            } else {
+                Vec2::ZERO
+            };
+            let zoom = 1.0 - wheel_delta * controller.mouse_wheel_zoom_sensitivity;
+            events.send(if scheme == ControlScheme::Arcball {
+                ControlEvent::Tumble { rotation, zoom }
+            } else {
+                ControlEvent::Orbit { rotation, zoom }
+            });
+        }
+    } else if controls.pressed(Action::Locomotion) {
         locomotion += keyboard_mvmt_sensitivity * locomotion_dir;
         keyboard_mvmt_sensitivity += keyboard_mvmt_wheel_sensitivity * wheel_delta;
         controller.keyboard_mvmt_sensitivity = keyboard_mvmt_sensitivity.max(0.01);
@@ -220,7 +506,7 @@ pub fn default_input_map(
             translation: locomotion,
             rotation: mouse_rotate_sensitivity * cursor_delta,
         });
-    } else if left_pressed {
+    } else if controls.pressed(Action::Orbit) {
         let rotation = mouse_rotate_sensitivity * cursor_delta;
         let zoom = 1.0 - wheel_delta * controller.mouse_wheel_zoom_sensitivity;
         // let mut zoom = 1.0;
@@ -237,43 +523,68 @@ pub fn default_input_map(
         events.send(ControlEvent::Orbit { rotation, zoom });
     }
 
-    // You can also pan using the mouse only; add those signals to existing panning
-    // if middle_pressed || (left_pressed && right_pressed) {
-    //     panning += mouse_translate_sensitivity * cursor_delta;
-    // }
-
     // When left only is pressed, mouse movements add up to the "Unreal locomotion" scheme
     // if left_pressed && !middle_pressed && !right_pressed {
     //     locomotion.x = mouse_rotate_sensitivity.x * cursor_delta.x;
     //     locomotion.y -= mouse_translate_sensitivity.y * cursor_delta.y;
     // }
 
-    // if panning.length_squared() > 0.0 {
-    //     events.send(ControlEvent::TranslateEye(panning));
-    // }
-
     // if locomotion.length_squared() > 0.0 {
     //     events.send(ControlEvent::Locomotion(locomotion));
     // }
 }
 
+/// Swings the eye around the target to a view preset over
+/// [`PRESET_DURATION`], turning the up vector along with it. The smoother alone
+/// would move the eye in a straight line, through the target for opposite
+/// views.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct PresetAnimation {
+    /// Rotations taking +Z to the direction from the target to the eye, and +Y
+    /// to the up vector.
+    from: Quat,
+    to: Quat,
+    elapsed: f32,
+}
+
+/// Seconds a view preset takes to swing into place.
+pub const PRESET_DURATION: f32 = 0.3;
+
+#[allow(clippy::type_complexity)]
 pub fn control_system(
+    mut commands: Commands,
     time: Res<Time>,
+    world_up: Res<WorldUp>,
     mut events: EventReader<ControlEvent>,
-    mut cameras: Query<(&MyCameraController, &mut LookTransform)>,
+    mut cameras: Query<(
+        Entity,
+        &MyCameraController,
+        &mut LookTransform,
+        &mut Projection,
+        Option<&PresetAnimation>,
+    )>,
 ) {
     // Can only control one camera at a time.
-    let mut transform = if let Some((_, transform)) = cameras.iter_mut().find(|c| c.0.enabled) {
-        transform
-    } else {
-        return;
-    };
+    let (entity, scheme, mut transform, mut projection, animating) =
+        if let Some((entity, controller, transform, projection, animation)) =
+            cameras.iter_mut().find(|c| c.1.enabled)
+        {
+            let scheme = effective_scheme(controller.scheme, world_up.0);
+            (entity, scheme, transform, projection, animation.copied())
+        } else {
+            return;
+        };
+    let mut animation = animating;
 
     let look_vector = match transform.look_direction() {
         Some(safe_look_vector) => safe_look_vector,
         None => return,
     };
 
+    // Yaw and pitch are measured in a Y-up frame, turned to the world up by `frame`.
+    let frame = world_up.0.rotation();
+    let up = world_up.0.vector();
+
     let dt = time.delta_seconds();
     for event in events.iter() {
         match event {
@@ -287,14 +598,14 @@ pub fn control_system(
                 translation,
                 rotation,
             } => {
-                let mut look_angles = LookAngles::from_vector(look_vector);
+                let mut look_angles = LookAngles::from_vector(frame.inverse() * look_vector);
                 let radius = transform.radius();
                 // Rotates with pitch and yaw.
                 look_angles.add_yaw(dt * -rotation.x);
                 look_angles.add_pitch(dt * -rotation.y);
 
                 let yaw_rot = Quat::from_axis_angle(Vec3::Y, look_angles.get_yaw());
-                let left_right = yaw_rot * Vec3::X;
+                let left_right = frame * (yaw_rot * Vec3::X);
 
                 // let pitch_rot = Quat::from_axis_angle(Vec3::X, look_angles.get_pitch());
                 // let rot_y = pitch_rot * yaw_rot * Vec3::Y;
@@ -307,20 +618,279 @@ pub fn control_system(
                     + dt * translation.y * down_up
                     + dt * back_forward * translation.z;
 
-                transform.target = transform.eye + radius * look_angles.unit_vector();
+                transform.target = transform.eye + radius * (frame * look_angles.unit_vector());
+                transform.up = up;
             }
 
             ControlEvent::Orbit { rotation, zoom } => {
-                let mut look_angles = LookAngles::from_vector(-look_vector);
+                let mut look_angles = LookAngles::from_vector(frame.inverse() * -look_vector);
                 look_angles.add_yaw(dt * -rotation.x);
                 look_angles.add_pitch(dt * rotation.y);
                 // dbg!(zoom);
 
-                let new_radius = (zoom * transform.radius()).min(1000000.0).max(0.001);
-                transform.eye = transform.target + new_radius * look_angles.unit_vector();
+                let new_radius = zoomed_radius(&transform, &mut projection, *zoom);
+                transform.eye = transform.target + new_radius * (frame * look_angles.unit_vector());
+                transform.up = up;
+            }
+
+            ControlEvent::Tumble { rotation, zoom } => {
+                let down_up = perpendicular_up(look_vector, transform.up);
+                let left_right = look_vector.cross(down_up);
+                // Dragging turns the side facing the camera along with the mouse.
+                let axis = (rotation.x * down_up + rotation.y * left_right).normalize_or_zero();
+                let turn = Quat::from_axis_angle(axis, -dt * rotation.length());
+
+                let new_radius = zoomed_radius(&transform, &mut projection, *zoom);
+                transform.eye = transform.target - new_radius * (turn * look_vector);
+                transform.up = turn * down_up;
+            }
+
+            ControlEvent::Preset(preset) => {
+                animation = Some(PresetAnimation {
+                    from: look_rotation(-look_vector, transform.up),
+                    to: preset_rotation(*preset, world_up.0),
+                    elapsed: 0.0,
+                });
+            }
+
+            ControlEvent::TranslateEye(delta) => {
+                // Scaled by the size of the view so panning feels the same at any zoom.
+                let half_height = match &*projection {
+                    Projection::Perspective(perspective) => {
+                        transform.radius() * (perspective.fov / 2.0).tan()
+                    }
+                    Projection::Orthographic(orthographic) => orthographic.scale,
+                };
+                let left_right = look_vector.cross(transform.up).normalize_or_zero();
+                let down_up = left_right.cross(look_vector);
+                let translation = half_height * (-delta.x * left_right + delta.y * down_up);
+                transform.eye += translation;
+                transform.target += translation;
+            }
+        }
+        // Moving the camera by hand stops a preset from taking over again.
+        if !matches!(event, ControlEvent::Preset(_)) {
+            animation = None;
+        }
+    }
+
+    if let Some(animation) = &mut animation {
+        animation.elapsed += dt;
+        let t = (animation.elapsed / PRESET_DURATION).min(1.0);
+        let rotation = animation.from.slerp(animation.to, t);
+        transform.eye = transform.target + transform.radius() * (rotation * Vec3::Z);
+        transform.up = rotation * Vec3::Y;
+    }
+    match animation {
+        Some(animation) if animation.elapsed < PRESET_DURATION => {
+            commands.entity(entity).insert(animation);
+        }
+        _ if animating.is_some() => {
+            // The smooth-bevy-cameras controllers keep the eye off the poles
+            // but never turn the up vector back themselves.
+            if is_external(scheme) {
+                transform.up = up;
             }
+            commands.entity(entity).remove::<PresetAnimation>();
         }
+        _ => {}
     }
 
     // look_angles.assert_not_looking_up();
 }
+
+/// Applies a zoom factor, returning the new distance of the eye from the
+/// target.
+fn zoomed_radius(transform: &LookTransform, projection: &mut Projection, zoom: f32) -> f32 {
+    match projection {
+        // Orthographic views look the same from any distance, so scale them instead.
+        Projection::Orthographic(orthographic) => {
+            orthographic.scale = (zoom * orthographic.scale).clamp(1e-6, 1e6);
+            transform.radius()
+        }
+        Projection::Perspective(_) => (zoom * transform.radius()).clamp(0.001, 1e6),
+    }
+}
+
+/// The rotation taking +Z to the direction from the target to the eye of
+/// `preset`, and +Y to its up vector. Top and bottom views turn the up vector
+/// so it isn't parallel to the view direction; orbiting turns it back.
+fn preset_rotation(preset: ViewPreset, up_axis: UpAxis) -> Quat {
+    // From the target towards the eye.
+    let (direction, up) = match preset {
+        ViewPreset::Front => (Vec3::Z, Vec3::Y),
+        ViewPreset::Back => (Vec3::NEG_Z, Vec3::Y),
+        ViewPreset::Left => (Vec3::NEG_X, Vec3::Y),
+        ViewPreset::Right => (Vec3::X, Vec3::Y),
+        ViewPreset::Top => (Vec3::Y, Vec3::NEG_Z),
+        ViewPreset::Bottom => (Vec3::NEG_Y, Vec3::Z),
+        ViewPreset::Isometric => (Vec3::ONE.normalize(), Vec3::Y),
+    };
+    up_axis.rotation() * look_rotation(direction, up)
+}
+
+/// The rotation taking +Z to `direction` and +Y to `up` made perpendicular
+/// to it.
+fn look_rotation(direction: Vec3, up: Vec3) -> Quat {
+    let up = perpendicular_up(direction, up);
+    Quat::from_mat3(&Mat3::from_cols(up.cross(direction), up, direction))
+}
+
+/// `up` made perpendicular to the unit vector `direction`, or any unit vector
+/// perpendicular to it if they are parallel.
+pub fn perpendicular_up(direction: Vec3, up: Vec3) -> Vec3 {
+    let right = up
+        .cross(direction)
+        .try_normalize()
+        .unwrap_or_else(|| direction.any_orthonormal_vector());
+    direction.cross(right)
+}
+
+/// Makes `axis` the up axis, turning the camera around its target along with
+/// the world so that it keeps showing the same side of things.
+pub fn set_up_axis(world_up: &mut WorldUp, axis: UpAxis, transform: &mut LookTransform) {
+    let turn = axis.rotation() * world_up.0.rotation().inverse();
+    transform.eye = transform.target + turn * (transform.eye - transform.target);
+    transform.up = turn * transform.up;
+    world_up.0 = axis;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_settings_are_valid() {
+        assert!(MyCameraController::default().validate().is_ok());
+    }
+
+    #[test]
+    fn smoothing_weight_out_of_range() {
+        for weight in [-0.1, 1.0, f32::NAN] {
+            let settings = MyCameraController {
+                smoothing_weight: weight,
+                ..MyCameraController::default()
+            };
+            assert!(matches!(
+                settings.validate(),
+                Err(SettingsError::SmoothingWeight(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn pixels_per_line_not_positive() {
+        for pixels in [0.0, -53.0, f32::INFINITY, f32::NAN] {
+            let settings = MyCameraController {
+                pixels_per_line: pixels,
+                ..MyCameraController::default()
+            };
+            assert!(matches!(
+                settings.validate(),
+                Err(SettingsError::PixelsPerLine(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn sensitivities_not_finite() {
+        type Set = fn(&mut MyCameraController, f32);
+        let fields: [(&str, Set); 8] = [
+            ("rotate_sensitivity.x", |c, v| c.rotate_sensitivity.x = v),
+            ("rotate_sensitivity.y", |c, v| c.rotate_sensitivity.y = v),
+            ("mouse_translate_sensitivity.x", |c, v| {
+                c.mouse_translate_sensitivity.x = v
+            }),
+            ("mouse_translate_sensitivity.y", |c, v| {
+                c.mouse_translate_sensitivity.y = v
+            }),
+            ("wheel_translate_sensitivity", |c, v| {
+                c.wheel_translate_sensitivity = v
+            }),
+            ("keyboard_mvmt_sensitivity", |c, v| {
+                c.keyboard_mvmt_sensitivity = v
+            }),
+            ("keyboard_mvmt_wheel_sensitivity", |c, v| {
+                c.keyboard_mvmt_wheel_sensitivity = v
+            }),
+            ("mouse_wheel_zoom_sensitivity", |c, v| {
+                c.mouse_wheel_zoom_sensitivity = v
+            }),
+        ];
+        for (field, set) in fields {
+            for value in [f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+                let mut settings = MyCameraController::default();
+                set(&mut settings, value);
+                assert!(
+                    matches!(
+                        settings.validate(),
+                        Err(SettingsError::NotFinite { name, .. }) if name == field
+                    ),
+                    "{} = {}",
+                    field,
+                    value
+                );
+            }
+        }
+    }
+
+    const AXES: [UpAxis; 6] = [
+        UpAxis::PosX,
+        UpAxis::NegX,
+        UpAxis::PosY,
+        UpAxis::NegY,
+        UpAxis::PosZ,
+        UpAxis::NegZ,
+    ];
+
+    /// The direction a preset looks in and the direction up on screen.
+    fn preset_view(preset: ViewPreset, up_axis: UpAxis) -> (Vec3, Vec3) {
+        let rotation = preset_rotation(preset, up_axis);
+        (rotation * Vec3::NEG_Z, rotation * Vec3::Y)
+    }
+
+    fn assert_close(a: Vec3, b: Vec3) {
+        assert!(a.abs_diff_eq(b, 1e-6), "{} != {}", a, b);
+    }
+
+    #[test]
+    fn up_axis_vectors() {
+        let vectors = [
+            Vec3::X,
+            Vec3::NEG_X,
+            Vec3::Y,
+            Vec3::NEG_Y,
+            Vec3::Z,
+            Vec3::NEG_Z,
+        ];
+        for (axis, vector) in AXES.into_iter().zip(vectors) {
+            assert_close(axis.rotation() * Vec3::Y, vector);
+            assert_close(axis.vector(), vector);
+        }
+    }
+
+    #[test]
+    fn presets_with_y_and_z_up() {
+        assert_close(preset_view(ViewPreset::Front, UpAxis::PosY).0, Vec3::NEG_Z);
+        assert_close(preset_view(ViewPreset::Top, UpAxis::PosY).0, Vec3::NEG_Y);
+        // As documented on `UpAxis::rotation`.
+        assert_close(preset_view(ViewPreset::Front, UpAxis::PosZ).0, Vec3::Y);
+        let (top, top_up) = preset_view(ViewPreset::Top, UpAxis::PosZ);
+        assert_close(top, Vec3::NEG_Z);
+        assert_close(top_up, Vec3::Y);
+    }
+
+    #[test]
+    fn presets_follow_up_axis() {
+        for axis in AXES {
+            let up = axis.vector();
+            let (front, front_up) = preset_view(ViewPreset::Front, axis);
+            assert_close(front, axis.rotation() * Vec3::NEG_Z);
+            assert_close(front_up, up);
+            let (top, _) = preset_view(ViewPreset::Top, axis);
+            assert_close(top, -up);
+            let (bottom, _) = preset_view(ViewPreset::Bottom, axis);
+            assert_close(bottom, up);
+        }
+    }
+}
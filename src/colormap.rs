@@ -0,0 +1,120 @@
+use std::str::FromStr;
+
+use bevy::prelude::Color;
+
+use crate::comms::{Colormap, ColormapSettings};
+
+/// Shown for NaN scalars.
+const MISSING: [u8; 3] = [0x80, 0x80, 0x80];
+
+// sRGB colors at evenly spaced positions, sampled from the matplotlib maps.
+const VIRIDIS: &[[u8; 3]] = &[
+    [0x44, 0x01, 0x54],
+    [0x47, 0x2d, 0x7b],
+    [0x3b, 0x52, 0x8b],
+    [0x2c, 0x72, 0x8e],
+    [0x21, 0x91, 0x8c],
+    [0x28, 0xae, 0x80],
+    [0x5e, 0xc9, 0x62],
+    [0xad, 0xdc, 0x30],
+    [0xfd, 0xe7, 0x25],
+];
+const MAGMA: &[[u8; 3]] = &[
+    [0x00, 0x00, 0x04],
+    [0x1c, 0x10, 0x44],
+    [0x4f, 0x12, 0x7b],
+    [0x81, 0x25, 0x81],
+    [0xb5, 0x36, 0x7a],
+    [0xe5, 0x50, 0x64],
+    [0xfb, 0x88, 0x61],
+    [0xfe, 0xc2, 0x87],
+    [0xfc, 0xfd, 0xbf],
+];
+const COOLWARM: &[[u8; 3]] = &[
+    [0x3b, 0x4c, 0xc0],
+    [0x62, 0x82, 0xea],
+    [0x8d, 0xb0, 0xfe],
+    [0xb8, 0xd0, 0xf9],
+    [0xdd, 0xdd, 0xdd],
+    [0xf5, 0xc4, 0xad],
+    [0xf4, 0x9a, 0x7b],
+    [0xde, 0x60, 0x4d],
+    [0xb4, 0x04, 0x26],
+];
+const GRAYSCALE: &[[u8; 3]] = &[[0x00, 0x00, 0x00], [0xff, 0xff, 0xff]];
+
+impl FromStr for Colormap {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "viridis" => Ok(Self::Viridis),
+            "magma" => Ok(Self::Magma),
+            "coolwarm" => Ok(Self::Coolwarm),
+            "grayscale" | "gray" | "grey" => Ok(Self::Grayscale),
+            _ => Err(format!(
+                "unknown colormap {:?}, expected one of viridis, magma, coolwarm, grayscale",
+                s
+            )),
+        }
+    }
+}
+
+impl Colormap {
+    fn table(self) -> &'static [[u8; 3]] {
+        match self {
+            Self::Viridis => VIRIDIS,
+            Self::Magma => MAGMA,
+            Self::Coolwarm => COOLWARM,
+            Self::Grayscale => GRAYSCALE,
+        }
+    }
+
+    /// Color at `t` in 0..=1, interpolated in sRGB like matplotlib does.
+    pub fn sample(self, t: f32) -> Color {
+        let table = self.table();
+        let x = t.clamp(0., 1.) * (table.len() - 1) as f32;
+        let i = (x as usize).min(table.len() - 2);
+        let frac = x - i as f32;
+        let [r, g, b] = [0, 1, 2].map(|c| {
+            let lo = table[i][c] as f32;
+            let hi = table[i + 1][c] as f32;
+            (lo + (hi - lo) * frac) / 255.
+        });
+        Color::rgb(r, g, b)
+    }
+}
+
+/// The range scalars are mapped over: the fixed bounds in `settings`, with
+/// missing ones taken from the data.
+fn range(scalars: &[f32], settings: &ColormapSettings) -> (f32, f32) {
+    let finite = || scalars.iter().copied().filter(|s| s.is_finite());
+    let min = settings
+        .min
+        .unwrap_or_else(|| finite().fold(f32::INFINITY, f32::min));
+    let max = settings
+        .max
+        .unwrap_or_else(|| finite().fold(f32::NEG_INFINITY, f32::max));
+    (min, max)
+}
+
+/// Linear RGBA vertex colors for `scalars`, ready for `Mesh::ATTRIBUTE_COLOR`.
+pub fn colors(scalars: &[f32], settings: &ColormapSettings) -> Vec<[f32; 4]> {
+    let (min, max) = range(scalars, settings);
+    let span = if max > min { max - min } else { 1. };
+    let [r, g, b] = MISSING.map(|c| c as f32 / 255.);
+    let missing = Color::rgb(r, g, b).as_linear_rgba_f32();
+    scalars
+        .iter()
+        .map(|&s| {
+            if s.is_nan() {
+                missing
+            } else {
+                settings
+                    .colormap
+                    .sample((s - min) / span)
+                    .as_linear_rgba_f32()
+            }
+        })
+        .collect()
+}
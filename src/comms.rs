@@ -1,62 +1,240 @@
-use bevy::prelude::Vec3;
-use ndarray::Array2;
+use bevy::prelude::{Quat, Vec3};
+use ndarray::{Array1, Array2};
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::f32::consts::{FRAC_PI_2, PI};
 use std::io::{Read, Write};
-use std::net::{TcpListener, TcpStream};
-use std::sync::mpsc::{Receiver, Sender};
-
-pub fn listen(sender: Sender<Message>, receiver: Receiver<Response>) {
-    let listener = TcpListener::bind("localhost:6142").unwrap();
-    loop {
-        match listener.accept() {
-            Ok((mut stream, _addr)) => {
-                loop {
-                    let recv = Message::receive(&mut stream);
-                    match recv.unwrap() {
-                        Some(message) => {
-                            let should_respond = message.requires_response();
-                            sender.send(message).unwrap();
-                            if should_respond {
-                                let response = receiver.recv().unwrap();
-                                response.send(&mut stream).unwrap();
-                            }
-                        }
-                        None => break,
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::str::FromStr;
+use std::sync::mpsc::{channel, Sender};
+use std::sync::Arc;
+use std::thread;
+use thiserror::Error;
+
+#[cfg(feature = "serde")]
+use crate::camera::{MyCameraController, SettingsError};
+use crate::config::Options;
+use crate::mesh::{self, MeshError};
+use crate::queue::Queue;
+
+#[derive(Error, Debug)]
+pub enum BindError {
+    #[error("could not listen on {addr}: {source}. Is another viewer already running? Pick another port with --port or {env}, or pass --port-fallback to use a free one", env = crate::config::PORT_ENV)]
+    InUse {
+        addr: String,
+        source: std::io::Error,
+    },
+    #[error("could not listen on {addr}: {source}")]
+    Io {
+        addr: String,
+        source: std::io::Error,
+    },
+}
+
+/// Binds the address in `options`, falling back to an OS-assigned port if
+/// that is allowed and the requested one is taken. Only the first address the
+/// host resolves to is tried, the one clients connect to; binding another
+/// would leave clients talking to whoever holds the first.
+pub fn bind(options: &Options) -> Result<TcpListener, BindError> {
+    let addr = options.addr();
+    let resolved = match addr.to_socket_addrs().map(|mut addrs| addrs.next()) {
+        Ok(Some(resolved)) => resolved,
+        Ok(None) => {
+            return Err(BindError::Io {
+                addr,
+                source: std::io::Error::new(
+                    std::io::ErrorKind::AddrNotAvailable,
+                    "host resolves to no address",
+                ),
+            })
+        }
+        Err(source) => return Err(BindError::Io { addr, source }),
+    };
+    match TcpListener::bind(resolved) {
+        Ok(listener) => Ok(listener),
+        Err(source) if source.kind() == std::io::ErrorKind::AddrInUse => {
+            if !options.port_fallback {
+                return Err(BindError::InUse { addr, source });
+            }
+            let fallback = SocketAddr::new(resolved.ip(), 0);
+            TcpListener::bind(fallback).map_err(|source| BindError::Io {
+                addr: fallback.to_string(),
+                source,
+            })
+        }
+        Err(source) => Err(BindError::Io { addr, source }),
+    }
+}
+
+/// A message from one client together with the channel its responses go back on.
+pub struct Envelope {
+    pub message: Message,
+    pub respond: Sender<Response>,
+}
+
+impl Envelope {
+    pub fn reply(&self, response: Response) {
+        // The client may have disconnected in the meantime; nothing to do then.
+        let _ = self.respond.send(response);
+    }
+}
+
+/// Accepts clients forever, serving each one on its own thread. Messages from
+/// all clients are merged into `queue`.
+pub fn listen(listener: TcpListener, queue: Arc<Queue<Envelope>>, max_message_size: u64) {
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let queue = queue.clone();
+                thread::spawn(move || {
+                    if let Err(e) = handle_client(stream, &queue, max_message_size) {
+                        eprintln!("ssmv: client error: {}", e);
                     }
-                }
-                // handle_client(stream, &sender)
+                });
             }
-            Err(_e) => eprintln!("errored"),
+            Err(e) => eprintln!("ssmv: failed to accept connection: {}", e),
         }
     }
 }
 
+fn handle_client(
+    mut stream: TcpStream,
+    queue: &Queue<Envelope>,
+    max_message_size: u64,
+) -> anyhow::Result<()> {
+    let (respond, responses) = channel();
+    while let Some(message) = Message::receive_limited(&mut stream, max_message_size)? {
+        if let Message::Hello {
+            protocol_version,
+            capabilities,
+        } = &message
+        {
+            let response = handshake(*protocol_version, capabilities);
+            let rejected = matches!(response, Response::Error { .. });
+            response.send(&mut stream)?;
+            if rejected {
+                break;
+            }
+            // Handshakes never reach the queue, so the viewer has no reply
+            // path for them.
+            continue;
+        }
+        if let Err(e) = message.validate() {
+            Response::from(e).send(&mut stream)?;
+            continue;
+        }
+        let should_respond = message.requires_response();
+        let acknowledged = message.acknowledged();
+        let evicted = queue.push(Envelope {
+            message,
+            respond: respond.clone(),
+        });
+        if let Some(evicted) = evicted.filter(|e| e.message.requires_response()) {
+            evicted.reply(Response::Error {
+                kind: ErrorKind::Overloaded,
+                message: "viewer is overloaded, request was dropped".to_string(),
+            });
+        }
+        if acknowledged {
+            Response::Ack.send(&mut stream)?;
+        }
+        if should_respond {
+            let response = responses.recv()?;
+            response.send(&mut stream)?;
+        }
+    }
+    Ok(())
+}
+
+/// Every frame starts with these bytes, so a peer speaking something else is
+/// rejected before its length prefix is trusted.
+pub const FRAME_MAGIC: [u8; 4] = *b"SSMV";
+/// Magic followed by the payload length as a little-endian `u64`.
+pub const FRAME_HEADER_LEN: usize = 12;
+pub const DEFAULT_MAX_MESSAGE_SIZE: u64 = 1 << 30;
+
+#[derive(Error, Debug)]
+pub enum FrameError {
+    #[error("connection closed {got} bytes into a {expected} byte {part}")]
+    Truncated {
+        part: &'static str,
+        expected: u64,
+        got: u64,
+    },
+    #[error("frame of {len} bytes exceeds the maximum message size of {max} bytes")]
+    TooLarge { len: u64, max: u64 },
+    #[error("bad frame magic {0:?}, is the peer an ssmv client?")]
+    BadMagic([u8; 4]),
+    #[error("could not encode or decode message: {0}")]
+    Codec(#[from] bincode::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// Like `read_exact`, but reports how far it got. Returns the number of bytes
+/// read, which is only short of `buf.len()` if the stream ended.
+fn read_full(stream: &mut impl Read, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match stream.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
 pub trait Communication: Serialize + DeserializeOwned + Send {
-    fn send(&self, stream: &mut TcpStream) -> anyhow::Result<()> {
+    fn send(&self, stream: &mut impl Write) -> Result<(), FrameError> {
         let bytes = bincode::serialize(self)?;
-        // let str = serde_json::to_string_pretty(self)?;
-        // let bytes = str.as_bytes();
-        let len = usize::to_le_bytes(bytes.len());
-        stream.write_all(&len)?;
+        let mut header = [0; FRAME_HEADER_LEN];
+        header[..4].copy_from_slice(&FRAME_MAGIC);
+        header[4..].copy_from_slice(&(bytes.len() as u64).to_le_bytes());
+        stream.write_all(&header)?;
         stream.write_all(&bytes)?;
 
         Ok(())
     }
 
-    fn receive(stream: &mut TcpStream) -> anyhow::Result<Option<Self>> {
-        let mut len = [0; std::mem::size_of::<usize>()];
-        let n_read = stream.read(&mut len)?;
+    fn receive(stream: &mut impl Read) -> Result<Option<Self>, FrameError> {
+        Self::receive_limited(stream, DEFAULT_MAX_MESSAGE_SIZE)
+    }
+
+    /// Reads one frame, returning `None` if the stream ended cleanly before it.
+    fn receive_limited(stream: &mut impl Read, max_size: u64) -> Result<Option<Self>, FrameError> {
+        let mut header = [0; FRAME_HEADER_LEN];
+        let n_read = read_full(stream, &mut header)?;
         if n_read == 0 {
             return Ok(None);
         }
-        let len = usize::from_le_bytes(len);
-        let mut message = vec![0; len];
-        stream.read_exact(&mut message)?;
+        if n_read < FRAME_HEADER_LEN {
+            return Err(FrameError::Truncated {
+                part: "header",
+                expected: FRAME_HEADER_LEN as u64,
+                got: n_read as u64,
+            });
+        }
+        let magic: [u8; 4] = header[..4].try_into().unwrap();
+        if magic != FRAME_MAGIC {
+            return Err(FrameError::BadMagic(magic));
+        }
+        let len = u64::from_le_bytes(header[4..].try_into().unwrap());
+        if len > max_size {
+            return Err(FrameError::TooLarge { len, max: max_size });
+        }
+
+        let mut message = vec![0; len as usize];
+        let n_read = read_full(stream, &mut message)?;
+        if n_read < message.len() {
+            return Err(FrameError::Truncated {
+                part: "message",
+                expected: len,
+                got: n_read as u64,
+            });
+        }
         let message: Self = bincode::deserialize(&message)?;
-        // let str = std::str::from_utf8(&message[..]).unwrap();
-        // eprintln!("{}", str);
-        // let message: Self = serde_json::from_slice(&message)?;
 
         Ok(Some(message))
     }
@@ -64,34 +242,765 @@ pub trait Communication: Serialize + DeserializeOwned + Send {
 
 impl<T: Serialize + DeserializeOwned + Send> Communication for T {}
 
+/// Bumped whenever the encoding of [`Message`] or [`Response`] changes.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Features this viewer supports, reported to clients in [`ServerInfo`].
+pub const CAPABILITIES: &[&str] = &[
+    "mesh",
+    "set_view",
+    "request_view",
+    "remove",
+    "clear",
+    "set_material",
+    "scalars",
+    "set_colormap",
+    "set_wireframe",
+    "shading",
+    "normals",
+    "uvs",
+    "polygons",
+    "winding",
+    "fit_view",
+    "set_projection",
+    "view_preset",
+    "control_scheme",
+    "arcball",
+    "up_axis",
+    #[cfg(feature = "serde")]
+    "camera_settings",
+];
+
+/// Object replaced by meshes sent without a name.
+pub const DEFAULT_OBJECT: &str = "default";
+
+/// Crease angle in degrees used when smooth shading is chosen without one,
+/// from Python or by cycling shading with the keyboard.
+pub const DEFAULT_CREASE_ANGLE: f32 = 30.;
+
+fn handshake(protocol_version: u32, capabilities: &[String]) -> Response {
+    if protocol_version != PROTOCOL_VERSION {
+        return Response::Error {
+            kind: ErrorKind::ProtocolMismatch,
+            message: format!(
+                "client speaks protocol version {}, but viewer {} speaks version {}; \
+                 install matching versions of the viewer and the client",
+                protocol_version,
+                env!("CARGO_PKG_VERSION"),
+                PROTOCOL_VERSION
+            ),
+        };
+    }
+    let missing: Vec<_> = capabilities
+        .iter()
+        .filter(|capability| !CAPABILITIES.contains(&capability.as_str()))
+        .map(String::as_str)
+        .collect();
+    if !missing.is_empty() {
+        return Response::Error {
+            kind: ErrorKind::ProtocolMismatch,
+            message: format!("viewer does not support {}", missing.join(", ")),
+        };
+    }
+    Response::ServerInfo(ServerInfo::current())
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct View {
     pub position: Vec3,
     pub look_at: Vec3,
 }
 
+#[derive(Error, Debug)]
+pub enum ViewError {
+    #[error("view position and look_at must be finite, got {position} and {look_at}")]
+    NotFinite { position: Vec3, look_at: Vec3 },
+    #[error("view position and look_at must differ, both are {0}")]
+    Coincident(Vec3),
+}
+
+impl View {
+    /// Rejects views without a look direction, which the camera controllers
+    /// can't follow.
+    pub fn validate(&self) -> Result<(), ViewError> {
+        if !self.position.is_finite() || !self.look_at.is_finite() {
+            return Err(ViewError::NotFinite {
+                position: self.position,
+                look_at: self.look_at,
+            });
+        }
+        if (self.position - self.look_at).length_squared() <= f32::EPSILON {
+            return Err(ViewError::Coincident(self.position));
+        }
+        Ok(())
+    }
+}
+
+/// Axis-aligned views of the camera target, named for the side they show.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViewPreset {
+    /// Looking along -Z.
+    Front,
+    Back,
+    /// Looking along +X.
+    Left,
+    Right,
+    /// Looking down along -Y.
+    Top,
+    Bottom,
+    /// Looking at the front, right and top at equal angles.
+    Isometric,
+}
+
+impl FromStr for ViewPreset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "front" => Ok(Self::Front),
+            "back" => Ok(Self::Back),
+            "left" => Ok(Self::Left),
+            "right" => Ok(Self::Right),
+            "top" => Ok(Self::Top),
+            "bottom" => Ok(Self::Bottom),
+            "isometric" => Ok(Self::Isometric),
+            _ => Err(format!(
+                "unknown view preset {:?}, expected one of front, back, left, right, top, \
+                 bottom, isometric",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ProjectionMode {
+    #[default]
+    Perspective,
+    /// Parallel projection, showing sizes independent of depth.
+    Orthographic,
+}
+
+impl FromStr for ProjectionMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "perspective" => Ok(Self::Perspective),
+            "orthographic" => Ok(Self::Orthographic),
+            _ => Err(format!(
+                "unknown projection {:?}, expected \"perspective\" or \"orthographic\"",
+                s
+            )),
+        }
+    }
+}
+
+/// The world axis that points up on screen when orbiting, and that view
+/// presets are named relative to.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UpAxis {
+    PosX,
+    NegX,
+    #[default]
+    PosY,
+    NegY,
+    PosZ,
+    NegZ,
+}
+
+impl UpAxis {
+    pub fn vector(self) -> Vec3 {
+        self.rotation() * Vec3::Y
+    }
+
+    /// Turns a Y-up world into one with this axis up. With Z up, front views
+    /// look along +Y and top views have +Y up on screen.
+    pub fn rotation(self) -> Quat {
+        match self {
+            Self::PosX => Quat::from_rotation_z(-FRAC_PI_2),
+            Self::NegX => Quat::from_rotation_z(FRAC_PI_2),
+            Self::PosY => Quat::IDENTITY,
+            Self::NegY => Quat::from_rotation_x(PI),
+            Self::PosZ => Quat::from_rotation_x(FRAC_PI_2),
+            Self::NegZ => Quat::from_rotation_x(-FRAC_PI_2),
+        }
+    }
+}
+
+impl FromStr for UpAxis {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "x" | "+x" => Ok(Self::PosX),
+            "-x" => Ok(Self::NegX),
+            "y" | "+y" => Ok(Self::PosY),
+            "-y" => Ok(Self::NegY),
+            "z" | "+z" => Ok(Self::PosZ),
+            "-z" => Ok(Self::NegZ),
+            _ => Err(format!(
+                "unknown up axis {:?}, expected \"x\", \"y\", \"z\", \"-x\", \"-y\" or \"-z\"",
+                s
+            )),
+        }
+    }
+}
+
+/// How mouse and keys move the camera.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ControlScheme {
+    /// Orbit with the mouse, or fly with the mouse and keys while locomotion
+    /// is held.
+    #[default]
+    Custom,
+    /// The orbit controller of smooth-bevy-cameras.
+    Orbit,
+    /// The Unreal editor style fly controller of smooth-bevy-cameras.
+    Unreal,
+    /// Orbit and pan only, zooming with the wheel, with the up axis kept
+    /// vertical on screen.
+    Turntable,
+    /// Like `Turntable`, but rotating freely as if dragging a ball, so the
+    /// camera can tumble over the top of things.
+    Arcball,
+}
+
+impl ControlScheme {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Custom => Self::Orbit,
+            Self::Orbit => Self::Unreal,
+            Self::Unreal => Self::Turntable,
+            Self::Turntable => Self::Arcball,
+            Self::Arcball => Self::Custom,
+        }
+    }
+}
+
+impl FromStr for ControlScheme {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "custom" => Ok(Self::Custom),
+            "orbit" => Ok(Self::Orbit),
+            "unreal" => Ok(Self::Unreal),
+            "turntable" => Ok(Self::Turntable),
+            "arcball" => Ok(Self::Arcball),
+            _ => Err(format!(
+                "unknown control scheme {:?}, expected \"custom\", \"orbit\", \"unreal\", \"turntable\" or \"arcball\"",
+                s
+            )),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
+pub struct MeshData {
+    /// Name of the object to create or replace.
+    pub id: String,
+    pub verts: Array2<f32>,
+    pub faces: Faces,
+    /// One value per vertex, shown through the object's colormap.
+    pub vertex_scalars: Option<Array1<f32>>,
+    /// One value per face, shown through the object's colormap.
+    pub face_scalars: Option<Array1<f32>>,
+    /// Overrides the global shading for this object.
+    pub shading: Option<Shading>,
+    /// Which side of the faces is the front.
+    pub winding: Winding,
+    /// Frame all objects once this one is shown. Turn off for updates of an
+    /// animation so the camera stays put.
+    pub auto_fit: bool,
+    /// One normal per vertex, used instead of normals computed from the faces.
+    pub normals: Option<Array2<f32>>,
+    /// One texture coordinate per vertex.
+    pub uvs: Option<Array2<f32>>,
+}
+
+impl MeshData {
+    /// Mesh data without any of the optional attributes.
+    pub fn new(id: impl Into<String>, verts: Array2<f32>, faces: impl Into<Faces>) -> Self {
+        Self {
+            id: id.into(),
+            verts,
+            faces: faces.into(),
+            vertex_scalars: None,
+            face_scalars: None,
+            shading: None,
+            winding: Winding::default(),
+            auto_fit: true,
+            normals: None,
+            uvs: None,
+        }
+    }
+}
+
+/// Faces as indices into the vertices, triangulated by the viewer.
+#[derive(Serialize, Deserialize, Debug)]
+pub enum Faces {
+    /// One face per row, all with the same number of corners, such as (M, 3)
+    /// for triangles or (M, 4) for quads.
+    Uniform(Array2<i32>),
+    /// Faces with any number of corners, face `i` being
+    /// `indices[offsets[i]..offsets[i + 1]]`. `offsets` has one more entry
+    /// than there are faces, starting at 0 and ending at `indices.len()`.
+    Polygons {
+        offsets: Array1<i32>,
+        indices: Array1<i32>,
+    },
+}
+
+impl From<Array2<i32>> for Faces {
+    fn from(faces: Array2<i32>) -> Self {
+        Self::Uniform(faces)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Winding {
+    /// Faces are oriented consistently by the viewer, pointing outward.
+    #[default]
+    Auto,
+    /// Front faces list their corners counter-clockwise.
+    Ccw,
+    /// Front faces list their corners clockwise, as in the output of
+    /// skimage's marching cubes.
+    Cw,
+}
+
+impl FromStr for Winding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Self::Auto),
+            "ccw" => Ok(Self::Ccw),
+            "cw" => Ok(Self::Cw),
+            _ => Err(format!(
+                "unknown winding {:?}, expected \"auto\", \"ccw\" or \"cw\"",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum Shading {
+    /// One normal per face.
+    #[default]
+    Flat,
+    /// Vertex normals averaged over the surrounding faces, weighted by area.
+    Smooth,
+    /// Like `Smooth`, but edges where faces meet at more than this many
+    /// degrees stay sharp.
+    Crease(f32),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Colormap {
+    #[default]
+    Viridis,
+    Magma,
+    Coolwarm,
+    Grayscale,
+}
+
+/// How scalars are turned into colors. Bounds left as `None` follow the data.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct ColormapSettings {
+    pub colormap: Colormap,
+    pub min: Option<f32>,
+    pub max: Option<f32>,
+}
+
+/// Material changes for an object. Settings left as `None` are kept as they are.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct MaterialSettings {
+    /// sRGB base color, components in 0..=1.
+    pub color: Option<[f32; 3]>,
+    /// Below 1 the object is alpha blended.
+    pub opacity: Option<f32>,
+    pub metallic: Option<f32>,
+    pub roughness: Option<f32>,
+    /// sRGB emitted light, components in 0..=1.
+    pub emissive: Option<[f32; 3]>,
+    /// Ignore lighting and show the base color as is.
+    pub unlit: Option<bool>,
+    /// Hide faces pointing away from the camera instead of drawing both sides.
+    pub cull_backfaces: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WireframeMode {
+    /// Shaded surface only.
+    #[default]
+    Off,
+    /// Face edges drawn on top of the shaded surface.
+    Edges,
+    /// Face edges without the surface.
+    Only,
+}
+
+impl FromStr for WireframeMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(Self::Off),
+            "edges" => Ok(Self::Edges),
+            "only" => Ok(Self::Only),
+            _ => Err(format!(
+                "unknown wireframe mode {:?}, expected \"off\", \"edges\" or \"only\"",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ServerInfo {
+    /// Crate version of the viewer.
+    pub version: String,
+    pub protocol_version: u32,
+    pub capabilities: Vec<String>,
+}
+
+impl ServerInfo {
+    pub fn current() -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            protocol_version: PROTOCOL_VERSION,
+            capabilities: CAPABILITIES.iter().map(|c| c.to_string()).collect(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    ProtocolMismatch,
+    InvalidMesh,
+    /// The request was dropped because the viewer could not keep up.
+    Overloaded,
+    InvalidSettings,
+    InvalidView,
+}
+
+/// Why the listener rejected a message before queueing it.
+#[derive(Error, Debug)]
+pub enum MessageError {
+    #[error(transparent)]
+    Mesh(#[from] MeshError),
+    #[error(transparent)]
+    View(#[from] ViewError),
+    #[cfg(feature = "serde")]
+    #[error(transparent)]
+    CameraSettings(#[from] SettingsError),
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[allow(clippy::large_enum_variant)]
 pub enum Message {
-    Mesh {
-        verts: Array2<f32>,
-        faces: Array2<i32>,
+    /// Sent by clients right after connecting. Must stay the first variant so
+    /// that it decodes the same in every protocol version.
+    Hello {
+        protocol_version: u32,
+        /// Capabilities the client can't work without. The viewer rejects
+        /// the handshake if it lacks any of them. Clients check capabilities
+        /// they only need for some calls in [`ServerInfo`] instead.
+        capabilities: Vec<String>,
     },
+    Mesh(MeshData),
     SetView(View),
     RequestView,
+    /// Deletes the object with this id, if there is one.
+    Remove {
+        id: String,
+    },
+    /// Deletes all objects.
+    Clear,
+    SetMaterial {
+        id: String,
+        material: MaterialSettings,
+    },
+    /// Recolors the scalars of an object without resending its geometry.
+    SetColormap {
+        id: String,
+        colormap: ColormapSettings,
+    },
+    /// Sets the wireframe mode of one object, or without an id the mode of
+    /// all objects that don't have their own.
+    SetWireframe {
+        id: Option<String>,
+        mode: WireframeMode,
+    },
+    /// Sets the shading of one object, or without an id the shading of all
+    /// objects that don't have their own.
+    SetShading {
+        id: Option<String>,
+        shading: Shading,
+    },
+    /// Points the camera at the given objects so they fill the view, or at all
+    /// objects if no ids are given.
+    FitView {
+        ids: Vec<String>,
+    },
+    /// Switches between perspective and orthographic projection, keeping the
+    /// apparent size of things at the camera target.
+    SetProjection(ProjectionMode),
+    /// Moves the camera around its target to an axis-aligned view.
+    SetViewPreset(ViewPreset),
+    /// Switches how the camera is controlled, keeping its eye and target.
+    SetControlScheme(ControlScheme),
+    /// Turns the camera along with the world so the given axis points up,
+    /// keeping the view relative to the old up axis.
+    SetUpAxis(UpAxis),
+    // Variants that depend on cargo features stay last so that the others
+    // decode the same in every build.
+    /// Replaces the settings of the camera controller, which the viewer keeps
+    /// for the next session. Whether the camera listens to input is kept as it
+    /// is.
+    #[cfg(feature = "serde")]
+    SetCameraSettings(MyCameraController),
+    #[cfg(feature = "serde")]
+    RequestCameraSettings,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum CoalesceKey {
+    Mesh(String),
+    View,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub enum Response {
+    /// Reply to [`Message::Hello`]. This and `Error` must stay the first two
+    /// variants so that old clients can still read a rejection.
+    ServerInfo(ServerInfo),
+    Error {
+        kind: ErrorKind,
+        message: String,
+    },
     GetView(View),
+    /// A message was accepted by the viewer.
+    Ack,
     Other,
+    /// Reply to [`Message::RequestCameraSettings`].
+    #[cfg(feature = "serde")]
+    CameraSettings(MyCameraController),
+}
+
+impl From<MessageError> for Response {
+    fn from(e: MessageError) -> Self {
+        let kind = match e {
+            MessageError::Mesh(_) => ErrorKind::InvalidMesh,
+            MessageError::View(_) => ErrorKind::InvalidView,
+            #[cfg(feature = "serde")]
+            MessageError::CameraSettings(_) => ErrorKind::InvalidSettings,
+        };
+        Self::Error {
+            kind,
+            message: e.to_string(),
+        }
+    }
 }
 
 impl Message {
+    /// A handshake requiring nothing beyond the protocol version.
+    pub fn hello() -> Self {
+        Self::Hello {
+            protocol_version: PROTOCOL_VERSION,
+            capabilities: vec![],
+        }
+    }
+
+    /// Rejects messages the viewer could not apply.
+    pub fn validate(&self) -> Result<(), MessageError> {
+        match self {
+            Self::Mesh(data) => mesh::validate(data)?,
+            Self::SetView(view) => view.validate()?,
+            #[cfg(feature = "serde")]
+            Self::SetCameraSettings(settings) => settings.validate()?,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Messages with the same key replace each other if several are waiting to
+    /// be applied, so only the newest one is.
+    pub fn coalesce_key(&self) -> Option<CoalesceKey> {
+        match self {
+            Self::Mesh(data) => Some(CoalesceKey::Mesh(data.id.clone())),
+            Self::SetView(_) => Some(CoalesceKey::View),
+            _ => None,
+        }
+    }
+
+    /// Whether the listener confirms with [`Response::Ack`] once the message is queued.
+    /// Lets clients see validation errors for messages that otherwise need no reply.
+    fn acknowledged(&self) -> bool {
+        match self {
+            Self::Mesh(_) => true,
+            Self::SetView(_) => true,
+            #[cfg(feature = "serde")]
+            Self::SetCameraSettings(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Whether the viewer itself replies to the message.
     fn requires_response(&self) -> bool {
         match self {
-            Self::Mesh { .. } => false,
+            // Answered by the listener before anything is queued.
+            Self::Hello { .. } => false,
+            Self::Mesh(_) => false,
             Self::SetView(_) => false,
             Self::RequestView => true,
+            Self::Remove { .. } => false,
+            Self::Clear => false,
+            Self::SetMaterial { .. } => false,
+            Self::SetColormap { .. } => false,
+            Self::SetWireframe { .. } => false,
+            Self::SetShading { .. } => false,
+            Self::FitView { .. } => false,
+            Self::SetProjection(_) => false,
+            Self::SetViewPreset(_) => false,
+            Self::SetControlScheme(_) => false,
+            Self::SetUpAxis(_) => false,
+            #[cfg(feature = "serde")]
+            Self::SetCameraSettings(_) => false,
+            #[cfg(feature = "serde")]
+            Self::RequestCameraSettings => true,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(magic: &[u8; 4], len: u64) -> Vec<u8> {
+        let mut bytes = magic.to_vec();
+        bytes.extend_from_slice(&len.to_le_bytes());
+        bytes
+    }
+
+    fn receive(bytes: &[u8], max_size: u64) -> Result<Option<Message>, FrameError> {
+        Message::receive_limited(&mut &bytes[..], max_size)
+    }
+
+    #[test]
+    fn view_without_direction() {
+        let view = |position, look_at| View { position, look_at }.validate();
+        assert!(view(Vec3::ZERO, Vec3::X).is_ok());
+        assert!(matches!(
+            view(Vec3::ONE, Vec3::ONE),
+            Err(ViewError::Coincident(_))
+        ));
+        assert!(matches!(
+            view(Vec3::new(f32::NAN, 0.0, 0.0), Vec3::X),
+            Err(ViewError::NotFinite { .. })
+        ));
+        assert!(matches!(
+            view(Vec3::ZERO, Vec3::splat(f32::INFINITY)),
+            Err(ViewError::NotFinite { .. })
+        ));
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut bytes = vec![];
+        Message::Clear.send(&mut bytes).unwrap();
+        assert!(matches!(receive(&bytes, 1024), Ok(Some(Message::Clear))));
+        assert!(matches!(receive(&[], 1024), Ok(None)));
+    }
+
+    #[test]
+    fn truncated_header() {
+        let bytes = &header(&FRAME_MAGIC, 4)[..5];
+        assert!(matches!(
+            receive(bytes, 1024),
+            Err(FrameError::Truncated {
+                part: "header",
+                expected: 12,
+                got: 5
+            })
+        ));
+    }
+
+    #[test]
+    fn truncated_message() {
+        let mut bytes = header(&FRAME_MAGIC, 10);
+        bytes.extend_from_slice(&[0; 3]);
+        assert!(matches!(
+            receive(&bytes, 1024),
+            Err(FrameError::Truncated {
+                part: "message",
+                expected: 10,
+                got: 3
+            })
+        ));
+    }
+
+    #[test]
+    fn bad_magic() {
+        let bytes = header(b"HTTP", 0);
+        assert!(matches!(
+            receive(&bytes, 1024),
+            Err(FrameError::BadMagic(magic)) if &magic == b"HTTP"
+        ));
+    }
+
+    #[test]
+    fn too_large() {
+        let bytes = header(&FRAME_MAGIC, 1025);
+        assert!(matches!(
+            receive(&bytes, 1024),
+            Err(FrameError::TooLarge {
+                len: 1025,
+                max: 1024
+            })
+        ));
+    }
+
+    #[test]
+    fn port_in_use() {
+        let taken = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut options = Options {
+            host: "127.0.0.1".to_string(),
+            port: taken.local_addr().unwrap().port(),
+            ..Options::default()
+        };
+        assert!(matches!(bind(&options), Err(BindError::InUse { .. })));
+        options.port_fallback = true;
+        let fallback = bind(&options).unwrap();
+        assert_ne!(fallback.local_addr().unwrap(), taken.local_addr().unwrap());
+    }
+
+    #[test]
+    fn handshake_checks_version_and_capabilities() {
+        let required = |capabilities: &[&str]| {
+            let capabilities: Vec<_> = capabilities.iter().map(|c| c.to_string()).collect();
+            handshake(PROTOCOL_VERSION, &capabilities)
+        };
+        assert!(matches!(required(&[]), Response::ServerInfo(_)));
+        assert!(matches!(
+            required(&["mesh", "up_axis"]),
+            Response::ServerInfo(_)
+        ));
+        assert!(matches!(
+            required(&["mesh", "teleport"]),
+            Response::Error { kind: ErrorKind::ProtocolMismatch, message }
+                if message.contains("teleport") && !message.contains("mesh")
+        ));
+        assert!(matches!(
+            handshake(PROTOCOL_VERSION + 1, &[]),
+            Response::Error {
+                kind: ErrorKind::ProtocolMismatch,
+                ..
+            }
+        ));
+    }
+}
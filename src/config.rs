@@ -0,0 +1,177 @@
+#[cfg(feature = "serde")]
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+use crate::bindings::{Bindings, BindingsError};
+use crate::comms::{UpAxis, DEFAULT_MAX_MESSAGE_SIZE};
+use crate::queue::Backpressure;
+
+pub const DEFAULT_HOST: &str = "localhost";
+pub const DEFAULT_PORT: u16 = 6142;
+pub const DEFAULT_QUEUE_CAPACITY: usize = 64;
+
+pub const HOST_ENV: &str = "SSMV_HOST";
+pub const PORT_ENV: &str = "SSMV_PORT";
+pub const BINDINGS_ENV: &str = "SSMV_BINDINGS";
+pub const UP_AXIS_ENV: &str = "SSMV_UP_AXIS";
+#[cfg(feature = "serde")]
+pub const CAMERA_SETTINGS_ENV: &str = "SSMV_CAMERA_SETTINGS";
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("invalid port {0:?}, expected a number between 0 and 65535")]
+    InvalidPort(String),
+    #[error("invalid size {0:?}, expected a non-negative integer")]
+    InvalidSize(String),
+    #[error("{0}")]
+    InvalidBackpressure(String),
+    #[error("{0}")]
+    InvalidUpAxis(String),
+    #[error("missing value for {0}")]
+    MissingValue(String),
+    #[error("unknown argument {0:?}")]
+    UnknownArgument(String),
+    #[error(transparent)]
+    Bindings(#[from] BindingsError),
+}
+
+/// Settings for a viewer instance. Later sources override earlier ones:
+/// defaults, then the environment, then command line flags.
+#[derive(Clone, Debug)]
+pub struct Options {
+    pub host: String,
+    /// Port to listen on. 0 lets the OS pick a free one.
+    pub port: u16,
+    /// When `port` is taken, listen on a free port instead of failing.
+    pub port_fallback: bool,
+    /// Frames announcing a larger payload are rejected before anything is allocated.
+    pub max_message_size: u64,
+    /// Messages waiting to be applied by the viewer before `backpressure` kicks in.
+    pub queue_capacity: usize,
+    pub backpressure: Backpressure,
+    /// Keys and mouse buttons of the camera and viewer controls.
+    pub bindings: Bindings,
+    pub up_axis: UpAxis,
+    /// JSON file keeping the camera controller settings between sessions.
+    #[cfg(feature = "serde")]
+    pub camera_settings: Option<PathBuf>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            host: DEFAULT_HOST.to_string(),
+            port: DEFAULT_PORT,
+            port_fallback: false,
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+            queue_capacity: DEFAULT_QUEUE_CAPACITY,
+            backpressure: Backpressure::Block,
+            bindings: Bindings::default(),
+            up_axis: UpAxis::default(),
+            #[cfg(feature = "serde")]
+            camera_settings: default_camera_settings(),
+        }
+    }
+}
+
+impl Options {
+    /// Defaults overridden by `SSMV_HOST` and `SSMV_PORT` only, which is all
+    /// a client needs to find the viewer.
+    pub fn addr_from_env() -> Result<Self, ConfigError> {
+        let mut options = Self::default();
+        if let Ok(host) = std::env::var(HOST_ENV) {
+            options.host = host;
+        }
+        if let Ok(port) = std::env::var(PORT_ENV) {
+            options.port = parse_port(&port)?;
+        }
+        Ok(options)
+    }
+
+    /// [`Options::addr_from_env`] further overridden by the viewer settings:
+    /// the bindings file named by `SSMV_BINDINGS`, `SSMV_UP_AXIS` and
+    /// `SSMV_CAMERA_SETTINGS`.
+    pub fn from_env() -> Result<Self, ConfigError> {
+        let mut options = Self::addr_from_env()?;
+        if let Ok(path) = std::env::var(BINDINGS_ENV) {
+            options.bindings = Bindings::load(path)?;
+        }
+        if let Ok(axis) = std::env::var(UP_AXIS_ENV) {
+            options.up_axis = axis.parse().map_err(ConfigError::InvalidUpAxis)?;
+        }
+        #[cfg(feature = "serde")]
+        if let Ok(path) = std::env::var(CAMERA_SETTINGS_ENV) {
+            options.camera_settings = settings_path(path);
+        }
+        Ok(options)
+    }
+
+    /// Parses `--host <host>`, `--port <port>`, `--port-fallback`,
+    /// `--max-message-size <bytes>`, `--queue-capacity <n>`,
+    /// `--backpressure <block|drop-oldest>`, `--bindings <json file>`,
+    /// `--up-axis <x|y|z|-x|-y|-z>` and `--camera-settings <json file>` on top
+    /// of [`Options::from_env`].
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Self, ConfigError> {
+        let mut options = Self::from_env()?;
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| ConfigError::MissingValue(arg.clone()))
+            };
+            match arg.as_str() {
+                "--host" => options.host = value()?,
+                "--port" => options.port = parse_port(&value()?)?,
+                "--port-fallback" => options.port_fallback = true,
+                "--max-message-size" => {
+                    let size = value()?;
+                    options.max_message_size =
+                        size.parse().map_err(|_| ConfigError::InvalidSize(size))?;
+                }
+                "--queue-capacity" => {
+                    let size = value()?;
+                    options.queue_capacity =
+                        size.parse().map_err(|_| ConfigError::InvalidSize(size))?;
+                }
+                "--backpressure" => {
+                    options.backpressure =
+                        value()?.parse().map_err(ConfigError::InvalidBackpressure)?;
+                }
+                "--bindings" => options.bindings = Bindings::load(value()?)?,
+                "--up-axis" => {
+                    options.up_axis = value()?.parse().map_err(ConfigError::InvalidUpAxis)?;
+                }
+                #[cfg(feature = "serde")]
+                "--camera-settings" => options.camera_settings = settings_path(value()?),
+                _ => return Err(ConfigError::UnknownArgument(arg)),
+            }
+        }
+        Ok(options)
+    }
+
+    pub fn addr(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+}
+
+/// `ssmv/camera.json` in the user's config directory.
+#[cfg(feature = "serde")]
+fn default_camera_settings() -> Option<PathBuf> {
+    let config = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config.join("ssmv").join("camera.json"))
+}
+
+/// An empty path turns off saving the camera settings.
+#[cfg(feature = "serde")]
+fn settings_path(path: String) -> Option<PathBuf> {
+    (!path.is_empty()).then(|| path.into())
+}
+
+fn parse_port(port: &str) -> Result<u16, ConfigError> {
+    port.parse()
+        .map_err(|_| ConfigError::InvalidPort(port.to_string()))
+}
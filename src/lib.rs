@@ -1,84 +1,282 @@
+pub mod bindings;
 mod camera;
+mod colormap;
 pub mod comms;
+pub mod config;
+mod material;
+pub mod mesh;
+pub mod queue;
 
 use std::{
-    sync::mpsc::{channel, Receiver, Sender},
+    collections::{hash_map::Entry, HashMap},
+    sync::Arc,
     thread, f32::consts::PI,
 };
 
 pub use bevy::prelude::Vec3;
 use bevy::{
-    pbr::{wireframe::{Wireframe, WireframeConfig, WireframePlugin}, NotShadowCaster},
+    pbr::NotShadowCaster,
     prelude::*,
     render::{
-        settings::{WgpuFeatures, WgpuSettings},
-        view::{NoFrustumCulling, ViewDepthTexture},
-        RenderPlugin,
+        camera::ScalingMode,
+        primitives::Aabb,
+        render_resource::PrimitiveTopology,
     },
 };
+pub use camera::MyCameraController;
 pub use comms::*;
-use ndarray::Axis;
+pub use config::Options;
+use bindings::{Action, Controls};
+use camera::ControlEvent;
+use mesh::Geometry;
+use queue::{coalesce, Queue};
 use smooth_bevy_cameras::{
     controllers::{
-        orbit::{OrbitCameraBundle, OrbitCameraController, OrbitCameraPlugin},
-        unreal::{UnrealCameraBundle, UnrealCameraController, UnrealCameraPlugin},
+        orbit::OrbitCameraPlugin,
+        unreal::UnrealCameraPlugin,
         // fps::{FpsCameraBundle, FpsCameraController, FpsCameraPlugin},
     },
     LookTransform, LookTransformPlugin,
 };
 
-pub fn run_rust() {
-    let (msender, mreceiver) = channel::<Message>();
-    let (rsender, rreceiver) = channel::<Response>();
+pub fn run_rust(options: Options) -> Result<(), BindError> {
+    let listener = bind(&options)?;
+    if let Ok(addr) = listener.local_addr() {
+        eprintln!("ssmv listening on {}", addr);
+    }
+
+    let queue = Arc::new(Queue::new(options.queue_capacity, options.backpressure));
 
-    thread::spawn(|| listen(msender, rreceiver));
+    let listen_queue = queue.clone();
+    let max_message_size = options.max_message_size;
+    thread::spawn(move || listen(listener, listen_queue, max_message_size));
 
     App::new()
         .add_startup_system(startup)
         .add_system(bevy_listen)
         .add_system(plane_transform)
-        .insert_non_send_resource(mreceiver)
-        .insert_non_send_resource(rsender)
-        .add_plugins(DefaultPlugins.set(RenderPlugin {
-            wgpu_settings: WgpuSettings {
-                features: WgpuFeatures::POLYGON_MODE_LINE,
-                ..default()
-            },
-        }))
-        .add_plugin(WireframePlugin)
+        .add_system(light_transform)
+        .add_system(wireframe_keys)
+        .add_system(shading_keys)
+        .add_system(fit_keys)
+        .add_system(projection_keys)
+        .insert_non_send_resource(queue)
+        .init_resource::<Objects>()
+        .init_resource::<GlobalWireframe>()
+        .init_resource::<GlobalShading>()
+        .add_plugins(DefaultPlugins)
         .add_plugin(LookTransformPlugin)
         .add_plugin(UnrealCameraPlugin::default())
         .add_plugin(OrbitCameraPlugin::default())
-        .add_plugin(camera::MyCameraPlugin::default())
+        .add_plugin(camera::MyCameraPlugin {
+            bindings: options.bindings.clone(),
+            up_axis: options.up_axis,
+            #[cfg(feature = "serde")]
+            settings_file: options.camera_settings.clone(),
+        })
         // .insert_resource(ClearColor(Color::rgba(0.4, 0.4, 0.4, 0.)))
         .run();
+
+    Ok(())
 }
 
+/// A mesh sent by a client, registered in [`Objects`] under its id.
 #[derive(Component)]
-struct TheMesh;
+struct SceneObject;
 
-#[derive(Component)]
-struct Flashlight;
+/// All objects in the scene, by id.
+#[derive(Resource, Default)]
+struct Objects(HashMap<String, Object>);
+
+/// Handles are kept here rather than looked up on the entity so that messages
+/// queued behind the one that spawned an object can modify it in the same frame.
+struct Object {
+    entity: Entity,
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+    /// Base color set by the client, kept while scalars replace it.
+    color: Color,
+    /// Child entity drawing the edges of the faces as they were sent.
+    edges: Entity,
+    edge_mesh: Handle<Mesh>,
+    geometry: Geometry,
+    /// Bounds of the geometry, used for frustum culling and to fit the view.
+    aabb: Aabb,
+    /// One per vertex of `mesh`, kept to recolor without resending the geometry.
+    scalars: Option<Vec<f32>>,
+    colormap: ColormapSettings,
+    /// Overrides [`GlobalWireframe`] for this object.
+    wireframe: Option<WireframeMode>,
+    /// Overrides [`GlobalShading`] for this object.
+    shading: Option<Shading>,
+}
+
+/// Wireframe mode of objects that don't set their own, cycled with Z by default.
+#[derive(Resource, Default)]
+struct GlobalWireframe(WireframeMode);
+
+/// Shading of objects that don't set their own, cycled with V by default.
+#[derive(Resource, Default)]
+struct GlobalShading(Shading);
+
+impl Object {
+    /// The edge mesh is only built while edges are shown, and emptied again
+    /// when they are hidden.
+    fn show_wireframe(
+        &self,
+        commands: &mut Commands,
+        meshes: &mut Assets<Mesh>,
+        global: &GlobalWireframe,
+    ) {
+        let mode = self.wireframe.unwrap_or(global.0);
+        if let Some(edges) = meshes.get_mut(&self.edge_mesh) {
+            match mode {
+                WireframeMode::Off => *edges = Mesh::new(PrimitiveTopology::LineList),
+                _ if edges.count_vertices() == 0 => *edges = mesh::edge_mesh(&self.geometry),
+                _ => {}
+            }
+        }
+        let edges = match mode {
+            WireframeMode::Off => Visibility::Hidden,
+            WireframeMode::Edges | WireframeMode::Only => Visibility::Inherited,
+        };
+        commands.entity(self.edges).insert(edges);
+        // Without a material only the edges are drawn.
+        let mut entity = commands.entity(self.entity);
+        match mode {
+            WireframeMode::Off | WireframeMode::Edges => entity.insert(self.material.clone()),
+            WireframeMode::Only => entity.remove::<Handle<StandardMaterial>>(),
+        };
+    }
+
+    /// Rebuilds the mesh from the geometry, for new geometry or shading.
+    fn rebuild(&mut self, meshes: &mut Assets<Mesh>, global: &GlobalShading) {
+        let built = mesh::build(&self.geometry, self.shading.unwrap_or(global.0));
+        if let Some(mesh) = meshes.get_mut(&self.mesh) {
+            *mesh = built.mesh;
+        }
+        self.scalars = built.scalars;
+        self.recolor(meshes);
+    }
+
+    /// Vertex colors are tinted by the base color, so it is white while the
+    /// object shows scalars and the client's color otherwise.
+    fn show_color(&self, materials: &mut Assets<StandardMaterial>) {
+        let Some(mat) = materials.get_mut(&self.material) else {
+            return;
+        };
+        let color = if self.scalars.is_some() {
+            Color::WHITE
+        } else {
+            self.color
+        };
+        mat.base_color = color.with_a(mat.base_color.a());
+    }
+
+    fn recolor(&self, meshes: &mut Assets<Mesh>) {
+        let Some(mesh) = meshes.get_mut(&self.mesh) else {
+            return;
+        };
+        match &self.scalars {
+            Some(scalars) => {
+                let colors = colormap::colors(scalars, &self.colormap);
+                mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+            }
+            None => {
+                mesh.remove_attribute(Mesh::ATTRIBUTE_COLOR);
+            }
+        }
+    }
+}
+
+/// Points the camera at the bounding sphere of `objects` and backs off along
+/// the view direction until the sphere fills the viewport.
+fn fit_view<'a>(
+    objects: impl IntoIterator<Item = &'a Object>,
+    lookat: &mut LookTransform,
+    projection: &mut Projection,
+) {
+    let Some((min, max)) = objects
+        .into_iter()
+        .map(|object| (Vec3::from(object.aabb.min()), Vec3::from(object.aabb.max())))
+        .reduce(|(min, max), (other_min, other_max)| (min.min(other_min), max.max(other_max)))
+    else {
+        return;
+    };
+    let center = (min + max) / 2.;
+    let radius = ((max - min).length() / 2.).max(1e-6);
+    let direction = lookat.look_direction().unwrap_or(Vec3::NEG_Z);
+    let distance = match projection {
+        Projection::Perspective(perspective) => {
+            // The narrower of the vertical and horizontal field of view decides.
+            let half_fov = perspective.fov / 2.;
+            let half_fov = half_fov.min((half_fov.tan() * perspective.aspect_ratio).atan());
+            let distance = radius / half_fov.sin();
+            // Keep the clipping planes well clear of objects of any scale.
+            perspective.near = distance * 1e-3;
+            perspective.far = (distance + radius) * 1e3;
+            distance
+        }
+        Projection::Orthographic(orthographic) => {
+            let aspect_ratio = orthographic.area.width() / orthographic.area.height();
+            let aspect_ratio = if aspect_ratio > 0. { aspect_ratio } else { 1. };
+            orthographic.scale = radius / aspect_ratio.min(1.);
+            let distance = 2. * radius;
+            orthographic.far = (distance + radius) * 1e3;
+            orthographic.near = -orthographic.far;
+            distance
+        }
+    };
+    lookat.target = center;
+    lookat.eye = center - distance * direction;
+}
+
+/// Switches the camera projection, keeping the size of things at the target.
+/// Orthographic views are zoomed by their scale rather than the eye distance,
+/// so the eye is moved to match when going back to perspective.
+fn set_projection(mode: ProjectionMode, lookat: &mut LookTransform, projection: &mut Projection) {
+    match (mode, &*projection) {
+        (ProjectionMode::Orthographic, Projection::Perspective(perspective)) => {
+            let half_height = lookat.radius() * (perspective.fov / 2.).tan();
+            *projection = Projection::Orthographic(OrthographicProjection {
+                scale: half_height,
+                scaling_mode: ScalingMode::FixedVertical(2.),
+                near: -perspective.far,
+                far: perspective.far,
+                ..default()
+            });
+        }
+        (ProjectionMode::Perspective, Projection::Orthographic(orthographic)) => {
+            let mut perspective = PerspectiveProjection {
+                far: orthographic.far,
+                ..default()
+            };
+            let radius = orthographic.scale / (perspective.fov / 2.).tan();
+            perspective.near = radius * 1e-3;
+            let direction = lookat.look_direction().unwrap_or(Vec3::NEG_Z);
+            lookat.eye = lookat.target - radius * direction;
+            *projection = Projection::Perspective(perspective);
+        }
+        _ => {}
+    }
+}
 
 #[derive(Component)]
 struct ViewPlane;
 
+/// The light fixed in the scene, placed above it relative to the up axis.
+#[derive(Component)]
+struct SceneLight;
+
+/// Where [`SceneLight`] is with Y up.
+const SCENE_LIGHT_POSITION: Vec3 = Vec3::new(-4.0, 8.0, 4.0);
+
 fn startup(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
-    mut wireframe_config: ResMut<WireframeConfig>,
+    world_up: Res<camera::WorldUp>,
 ) {
-    wireframe_config.global = false;
-    // commands
-    //     .spawn(Camera3dBundle::default())
-    //     .insert(UnrealCameraBundle::new(
-    //         UnrealCameraController::default(),
-    //         Vec3::new(-1., -1., -1.),
-    //         Vec3::new(0., 0., 0.),
-    //         Vec3::Y,
-    //     ));
-
     let mat = materials.add(StandardMaterial{
         double_sided: true,
         cull_mode: None,
@@ -97,9 +295,9 @@ fn startup(
         .spawn(Camera3dBundle::default())
         .insert(camera::MyCameraBundle::new(
             camera::MyCameraController::default(),
-            Vec3::new(-1., -1., -1.),
+            world_up.0.rotation() * Vec3::new(-1., -1., -1.),
             Vec3::new(0., 0., 0.),
-            Vec3::Y,
+            world_up.0.vector(),
         ))
         .insert(PointLightBundle {
             point_light: PointLight {
@@ -115,17 +313,6 @@ fn startup(
         });
     // commands.spawn(plane);
 
-    // commands
-    //     .spawn(Camera3dBundle::default())
-    //     .insert(OrbitCameraBundle::new(
-    //         OrbitCameraController::default(),
-    //         Vec3::new(-1., -1., -1.),
-    //         Vec3::new(
-    //             0., 0., 0.,
-    //         ),
-    //         Vec3::Y,
-    //     ));
-
     // commands.spawn(DirectionalLightBundle {
     //     directional_light: DirectionalLight {
     //         illuminance: 10000.,
@@ -141,49 +328,86 @@ fn startup(
         brightness: 0.2,
     });
     // light
-    commands.spawn(PointLightBundle {
-        point_light: PointLight {
-            intensity: 100000.0,
-            shadows_enabled: true,
-            range: 100.,
+    commands
+        .spawn(PointLightBundle {
+            point_light: PointLight {
+                intensity: 100000.0,
+                shadows_enabled: true,
+                range: 100.,
+                ..default()
+            },
+            transform: Transform::from_translation(world_up.0.rotation() * SCENE_LIGHT_POSITION),
             ..default()
-        },
-        transform: Transform::from_xyz(-4.0, 8.0, 4.0),
-        ..default()
-    });
+        })
+        .insert(SceneLight);
+}
+
+fn wireframe_keys(
+    mut commands: Commands,
+    controls: Controls,
+    objects: Res<Objects>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut global: ResMut<GlobalWireframe>,
+) {
+    if !controls.just_pressed(Action::CycleWireframe) {
+        return;
+    }
+    global.0 = match global.0 {
+        WireframeMode::Off => WireframeMode::Edges,
+        WireframeMode::Edges => WireframeMode::Only,
+        WireframeMode::Only => WireframeMode::Off,
+    };
+    for object in objects.0.values() {
+        object.show_wireframe(&mut commands, &mut meshes, &global);
+    }
+}
 
+fn shading_keys(
+    controls: Controls,
+    mut objects: ResMut<Objects>,
+    mut global: ResMut<GlobalShading>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    if !controls.just_pressed(Action::CycleShading) {
+        return;
+    }
+    global.0 = match global.0 {
+        Shading::Flat => Shading::Smooth,
+        Shading::Smooth => Shading::Crease(DEFAULT_CREASE_ANGLE),
+        Shading::Crease(_) => Shading::Flat,
+    };
+    for object in objects.0.values_mut() {
+        if object.shading.is_none() {
+            object.rebuild(&mut meshes, &global);
+        }
+    }
+}
 
-    
-
-    // commands
-    //     .spawn(PointLightBundle {
-    //         point_light: PointLight {
-    //             intensity: 100000.0,
-    //             shadows_enabled: true,
-    //             range: 100.,
-    //             ..default()
-    //         },
-    //         transform: Transform::from_xyz(-4.0, 8.0, 4.0),
-    //         ..default()
-    //     })
-    //     .insert(Flashlight);
-
-    let mut mesh = Mesh::new(bevy::render::render_resource::PrimitiveTopology::TriangleList);
-    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, vec![Vec3::new(0., 0., 0.)]);
-    let handle = meshes.add(mesh);
-
-    let mut mat: StandardMaterial = Color::rgb(0.3, 0.5, 0.3).into();
-    mat.cull_mode = None;
-    mat.double_sided = true;
-    commands
-        .spawn(TheMesh)
-        .insert(NoFrustumCulling)
-        .insert(PbrBundle {
-            mesh: handle.clone(),
-            material: materials.add(mat),
-            ..default()
-        });
-        // .insert(Wireframe);
+fn fit_keys(
+    controls: Controls,
+    objects: Res<Objects>,
+    mut camera: Query<(&mut LookTransform, &mut Projection)>,
+) {
+    if !controls.just_pressed(Action::FitView) {
+        return;
+    }
+    let (mut lookat, mut projection) = camera.single_mut();
+    fit_view(objects.0.values(), &mut lookat, &mut projection);
+}
+
+fn projection_keys(
+    controls: Controls,
+    mut camera: Query<(&mut LookTransform, &mut Projection)>,
+) {
+    if !controls.just_pressed(Action::ToggleProjection) {
+        return;
+    }
+    let (mut lookat, mut projection) = camera.single_mut();
+    let mode = match *projection {
+        Projection::Perspective(_) => ProjectionMode::Orthographic,
+        Projection::Orthographic(_) => ProjectionMode::Perspective,
+    };
+    set_projection(mode, &mut lookat, &mut projection);
 }
 
 fn plane_transform(
@@ -196,52 +420,217 @@ fn plane_transform(
     plane.scale = Vec3::splat(radius);
 }
 
+fn light_transform(
+    world_up: Res<camera::WorldUp>,
+    mut lights: Query<&mut Transform, With<SceneLight>>,
+) {
+    if !world_up.is_changed() {
+        return;
+    }
+    for mut light in &mut lights {
+        light.translation = world_up.0.rotation() * SCENE_LIGHT_POSITION;
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn bevy_listen(
-    receiver: NonSend<Receiver<Message>>,
-    sender: NonSend<Sender<Response>>,
-    query: Query<(&TheMesh, &Handle<Mesh>)>,
-    mut camera: Query<(&Camera, &mut LookTransform)>,
+    mut commands: Commands,
+    queue: NonSend<Arc<Queue<Envelope>>>,
+    mut objects: ResMut<Objects>,
+    mut global_wireframe: ResMut<GlobalWireframe>,
+    mut global_shading: ResMut<GlobalShading>,
+    mut camera: Query<(&mut LookTransform, &mut Projection)>,
+    mut controllers: Query<&mut MyCameraController>,
+    mut world_up: ResMut<camera::WorldUp>,
+    mut control_events: EventWriter<ControlEvent>,
     mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
 ) {
-    match receiver.try_recv() {
-        Ok(recv) => {
-            let (_, handle) = query.single();
-            let mesh = meshes.get_mut(handle).unwrap();
-            let (_, mut lookat) = camera.single_mut();
-
-            match recv {
-                Message::Mesh { verts, faces } => {
-                    let full_verts: Vec<Vec3> = faces
-                        .as_slice()
-                        .unwrap()
-                        .iter()
-                        .rev()
-                        .map(|&index| {
-                            let vert = verts.index_axis(Axis(0), index as usize);
-                            Vec3::new(vert[0], vert[1], vert[2])
+    // Apply everything that arrived since the last frame, skipping meshes and
+    // views that were already replaced by newer ones.
+    let envelopes = coalesce(queue.drain(), |envelope| envelope.message.coalesce_key());
+    if envelopes.is_empty() {
+        return;
+    }
+    let (mut lookat, mut projection) = camera.single_mut();
+
+    for envelope in envelopes {
+        match &envelope.message {
+            // Never queued: `handle_client` answers handshakes itself.
+            Message::Hello { .. } => {}
+            Message::Mesh(data) => {
+                let geometry = Geometry::new(data);
+                let (min, max) = geometry.bounds();
+                let aabb = Aabb::from_min_max(min, max);
+
+                let object = match objects.0.entry(data.id.clone()) {
+                    Entry::Occupied(entry) => {
+                        let object = entry.into_mut();
+                        object.geometry = geometry;
+                        object.aabb = aabb;
+                        object
+                    }
+                    Entry::Vacant(entry) => {
+                        // Filled in by the rebuild below.
+                        let mesh = meshes.add(Mesh::new(PrimitiveTopology::TriangleList));
+                        let material = materials.add(material::object_material());
+                        let edge_mesh = meshes.add(Mesh::new(PrimitiveTopology::LineList));
+                        let edges = commands
+                            .spawn(PbrBundle {
+                                mesh: edge_mesh.clone(),
+                                material: materials.add(material::edge_material()),
+                                ..default()
+                            })
+                            .insert(NotShadowCaster)
+                            .id();
+                        let entity = commands
+                            .spawn(SceneObject)
+                            .insert(PbrBundle {
+                                mesh: mesh.clone(),
+                                material: material.clone(),
+                                ..default()
+                            })
+                            .add_child(edges)
+                            .id();
+                        entry.insert(Object {
+                            entity,
+                            mesh,
+                            material,
+                            color: material::OBJECT_COLOR,
+                            edges,
+                            edge_mesh,
+                            geometry,
+                            aabb,
+                            scalars: None,
+                            colormap: default(),
+                            wireframe: None,
+                            shading: None,
                         })
-                        .collect();
-                    let com = full_verts.iter().sum::<Vec3>() / full_verts.len() as f32;
-                    lookat.target = com;
-
-                    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, full_verts);
-                    mesh.compute_flat_normals();
-                    // if let Some(aabb) = mesh.compute_aabb(){
-                    //     commands.entity(entity).insert(aabb);
-                    // }
+                    }
+                };
+                if data.shading.is_some() {
+                    object.shading = data.shading;
+                }
+                // Drop the edges of the old geometry, they are rebuilt if shown.
+                if let Some(edges) = meshes.get_mut(&object.edge_mesh) {
+                    *edges = Mesh::new(PrimitiveTopology::LineList);
+                }
+                object.show_wireframe(&mut commands, &mut meshes, &global_wireframe);
+                // Bevy only computes bounds for new meshes, so keep them current.
+                commands.entity(object.entity).insert(object.aabb);
+                commands.entity(object.edges).insert(object.aabb);
+                object.rebuild(&mut meshes, &global_shading);
+                object.show_color(&mut materials);
+                if data.auto_fit {
+                    fit_view(objects.0.values(), &mut lookat, &mut projection);
                 }
-                Message::SetView(View { position, look_at }) => {
-                    lookat.target = look_at;
-                    lookat.eye = position;
+            }
+            Message::SetView(View { position, look_at }) => {
+                lookat.target = *look_at;
+                lookat.eye = *position;
+                // A view along the up vector would leave the camera without a rotation.
+                if let Some(direction) = lookat.look_direction() {
+                    lookat.up = camera::perpendicular_up(direction, lookat.up);
+                }
+            }
+            Message::RequestView => {
+                let position = lookat.eye;
+                let look_at = lookat.target;
+                envelope.reply(Response::GetView(View { position, look_at }));
+            }
+            Message::Remove { id } => {
+                if let Some(object) = objects.0.remove(id) {
+                    commands.entity(object.entity).despawn_recursive();
                 }
-                Message::RequestView => {
-                    let position = lookat.eye;
-                    let look_at = lookat.target;
-                    let response = Response::GetView(View { position, look_at });
-                    sender.send(response).unwrap()
+            }
+            Message::Clear => {
+                for (_, object) in objects.0.drain() {
+                    commands.entity(object.entity).despawn_recursive();
+                }
+            }
+            Message::SetMaterial { id, material } => match objects.0.get_mut(id) {
+                Some(object) => {
+                    if let Some(mat) = materials.get_mut(&object.material) {
+                        material::apply(material, mat);
+                    }
+                    if let Some([r, g, b]) = material.color {
+                        object.color = Color::rgb(r, g, b);
+                    }
+                    object.show_color(&mut materials);
+                }
+                None => eprintln!("ssmv: SetMaterial for unknown object {:?}", id),
+            },
+            Message::SetColormap { id, colormap } => match objects.0.get_mut(id) {
+                Some(object) => {
+                    object.colormap = colormap.clone();
+                    object.recolor(&mut meshes);
+                }
+                None => eprintln!("ssmv: SetColormap for unknown object {:?}", id),
+            },
+            Message::SetWireframe { id: None, mode } => {
+                global_wireframe.0 = *mode;
+                for object in objects.0.values() {
+                    object.show_wireframe(&mut commands, &mut meshes, &global_wireframe);
                 }
             }
+            Message::SetWireframe { id: Some(id), mode } => match objects.0.get_mut(id) {
+                Some(object) => {
+                    object.wireframe = Some(*mode);
+                    object.show_wireframe(&mut commands, &mut meshes, &global_wireframe);
+                }
+                None => eprintln!("ssmv: SetWireframe for unknown object {:?}", id),
+            },
+            Message::SetShading { id: None, shading } => {
+                global_shading.0 = *shading;
+                for object in objects.0.values_mut() {
+                    if object.shading.is_none() {
+                        object.rebuild(&mut meshes, &global_shading);
+                    }
+                }
+            }
+            Message::SetShading {
+                id: Some(id),
+                shading,
+            } => match objects.0.get_mut(id) {
+                Some(object) => {
+                    object.shading = Some(*shading);
+                    object.rebuild(&mut meshes, &global_shading);
+                }
+                None => eprintln!("ssmv: SetShading for unknown object {:?}", id),
+            },
+            Message::FitView { ids } if ids.is_empty() => {
+                fit_view(objects.0.values(), &mut lookat, &mut projection);
+            }
+            Message::FitView { ids } => {
+                let fitted = ids.iter().filter_map(|id| {
+                    let object = objects.0.get(id);
+                    if object.is_none() {
+                        eprintln!("ssmv: FitView for unknown object {:?}", id);
+                    }
+                    object
+                });
+                fit_view(fitted, &mut lookat, &mut projection);
+            }
+            Message::SetProjection(mode) => {
+                set_projection(*mode, &mut lookat, &mut projection);
+            }
+            Message::SetViewPreset(preset) => control_events.send(ControlEvent::Preset(*preset)),
+            Message::SetUpAxis(axis) => camera::set_up_axis(&mut world_up, *axis, &mut lookat),
+            Message::SetControlScheme(scheme) => {
+                for mut controller in &mut controllers {
+                    controller.scheme = *scheme;
+                }
+            }
+            #[cfg(feature = "serde")]
+            Message::SetCameraSettings(settings) => {
+                for mut controller in &mut controllers {
+                    controller.replace_keeping_enabled(settings);
+                }
+            }
+            #[cfg(feature = "serde")]
+            Message::RequestCameraSettings => {
+                envelope.reply(Response::CameraSettings(*controllers.single()));
+            }
         }
-        Err(_) => (),
     }
 }
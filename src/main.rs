@@ -1,5 +1,15 @@
-use super_simple_mesh_viewer::run_rust;
+use super_simple_mesh_viewer::{run_rust, Options};
 
 fn main() {
-    run_rust();
+    let options = match Options::from_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("ssmv: {}", e);
+            std::process::exit(2);
+        }
+    };
+    if let Err(e) = run_rust(options) {
+        eprintln!("ssmv: {}", e);
+        std::process::exit(1);
+    }
 }
@@ -0,0 +1,57 @@
+use bevy::{prelude::*, render::render_resource::Face};
+
+use crate::comms::MaterialSettings;
+
+/// Base color of objects that don't set their own.
+pub const OBJECT_COLOR: Color = Color::rgb(0.3, 0.5, 0.3);
+
+/// Material of newly created objects.
+pub fn object_material() -> StandardMaterial {
+    let mut mat: StandardMaterial = OBJECT_COLOR.into();
+    mat.cull_mode = None;
+    mat.double_sided = true;
+    mat
+}
+
+/// Material of the lines drawn along the edges of objects in wireframe modes.
+pub fn edge_material() -> StandardMaterial {
+    StandardMaterial {
+        base_color: Color::WHITE,
+        unlit: true,
+        // Keeps the lines in front of the faces they lie on.
+        depth_bias: 1000.,
+        ..default()
+    }
+}
+
+/// Overwrites the parts of `mat` that `settings` specifies.
+pub fn apply(settings: &MaterialSettings, mat: &mut StandardMaterial) {
+    if let Some([r, g, b]) = settings.color {
+        mat.base_color = Color::rgba(r, g, b, mat.base_color.a());
+    }
+    if let Some(opacity) = settings.opacity {
+        let opacity = opacity.clamp(0., 1.);
+        mat.base_color.set_a(opacity);
+        mat.alpha_mode = if opacity < 1. {
+            AlphaMode::Blend
+        } else {
+            AlphaMode::Opaque
+        };
+    }
+    if let Some(metallic) = settings.metallic {
+        mat.metallic = metallic.clamp(0., 1.);
+    }
+    if let Some(roughness) = settings.roughness {
+        mat.perceptual_roughness = roughness.clamp(0., 1.);
+    }
+    if let Some([r, g, b]) = settings.emissive {
+        mat.emissive = Color::rgb(r, g, b);
+    }
+    if let Some(unlit) = settings.unlit {
+        mat.unlit = unlit;
+    }
+    if let Some(cull_backfaces) = settings.cull_backfaces {
+        mat.cull_mode = cull_backfaces.then_some(Face::Back);
+        mat.double_sided = !cull_backfaces;
+    }
+}
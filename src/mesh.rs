@@ -0,0 +1,973 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::{
+    prelude::*,
+    render::{
+        mesh::{Indices, Mesh},
+        render_resource::PrimitiveTopology,
+    },
+};
+use ndarray::{s, ArrayView1};
+use thiserror::Error;
+
+use crate::comms::{Faces, MeshData, Shading, Winding};
+
+#[derive(Error, Debug)]
+pub enum MeshError {
+    #[error("mesh has no vertices")]
+    NoVertices,
+    #[error("mesh has no faces")]
+    NoFaces,
+    #[error("verts must have shape (N, 3), got {0:?}")]
+    VertsShape([usize; 2]),
+    #[error("faces must have shape (M, K) with K >= 3, got {0:?}")]
+    FacesShape([usize; 2]),
+    #[error("face offsets must start at 0 and end at the number of indices, {0}")]
+    OffsetBounds(usize),
+    #[error("face {face} has {corners} corners, but faces need at least 3")]
+    TooFewCorners { face: usize, corners: i64 },
+    #[error("vertex {index} has a non-finite coordinate {value}")]
+    NonFinite { index: usize, value: f32 },
+    #[error("face {face} refers to vertex {index}, but there are only {n_verts} vertices")]
+    IndexOutOfRange {
+        face: usize,
+        index: i32,
+        n_verts: usize,
+    },
+    #[error("{name} must have shape ({n_verts}, {cols}), got {got:?}")]
+    AttributeShape {
+        name: &'static str,
+        n_verts: usize,
+        cols: usize,
+        got: [usize; 2],
+    },
+    #[error("got {got} {kind} scalars for {expected} {kind}s")]
+    ScalarsLength {
+        kind: &'static str,
+        expected: usize,
+        got: usize,
+    },
+    #[error("give either vertex or face scalars, not both")]
+    BothScalars,
+}
+
+/// Checks everything the viewer relies on when building a mesh, so bad input
+/// is reported to the client instead of taking the viewer down.
+pub fn validate(data: &MeshData) -> Result<(), MeshError> {
+    let MeshData { verts, faces, .. } = data;
+    if verts.ncols() != 3 {
+        return Err(MeshError::VertsShape([verts.nrows(), verts.ncols()]));
+    }
+    match faces {
+        Faces::Uniform(faces) if faces.ncols() < 3 => {
+            return Err(MeshError::FacesShape([faces.nrows(), faces.ncols()]));
+        }
+        Faces::Uniform(_) => {}
+        Faces::Polygons { offsets, indices } => {
+            let n_indices = indices.len();
+            if offsets.first() != Some(&0) || offsets.last() != Some(&(n_indices as i32)) {
+                return Err(MeshError::OffsetBounds(n_indices));
+            }
+            let ends = offsets.iter().zip(offsets.iter().skip(1));
+            for (face, (&start, &end)) in ends.enumerate() {
+                let corners = end as i64 - start as i64;
+                if corners < 3 {
+                    return Err(MeshError::TooFewCorners { face, corners });
+                }
+            }
+        }
+    }
+    if verts.nrows() == 0 {
+        return Err(MeshError::NoVertices);
+    }
+    if faces.is_empty() {
+        return Err(MeshError::NoFaces);
+    }
+
+    for (index, vert) in verts.outer_iter().enumerate() {
+        if let Some(&value) = vert.iter().find(|v| !v.is_finite()) {
+            return Err(MeshError::NonFinite { index, value });
+        }
+    }
+
+    let n_verts = verts.nrows();
+    for (face, indices) in faces.polygons().enumerate() {
+        if let Some(&index) = indices.iter().find(|&&i| i < 0 || i as usize >= n_verts) {
+            return Err(MeshError::IndexOutOfRange {
+                face,
+                index,
+                n_verts,
+            });
+        }
+    }
+
+    let attributes = [("normals", &data.normals, 3), ("uvs", &data.uvs, 2)];
+    for (name, attribute, cols) in attributes {
+        if let Some(attribute) = attribute {
+            if attribute.nrows() != n_verts || attribute.ncols() != cols {
+                return Err(MeshError::AttributeShape {
+                    name,
+                    n_verts,
+                    cols,
+                    got: [attribute.nrows(), attribute.ncols()],
+                });
+            }
+        }
+    }
+
+    if data.vertex_scalars.is_some() && data.face_scalars.is_some() {
+        return Err(MeshError::BothScalars);
+    }
+    let scalars = [
+        ("vertex", &data.vertex_scalars, verts.nrows()),
+        ("face", &data.face_scalars, faces.len()),
+    ];
+    for (kind, scalars, expected) in scalars {
+        if let Some(scalars) = scalars {
+            if scalars.len() != expected {
+                return Err(MeshError::ScalarsLength {
+                    kind,
+                    expected,
+                    got: scalars.len(),
+                });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+impl Faces {
+    /// Number of faces.
+    pub fn len(&self) -> usize {
+        match self {
+            Faces::Uniform(faces) => faces.nrows(),
+            Faces::Polygons { offsets, .. } => offsets.len().saturating_sub(1),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The corners of each face. Polygon offsets must have been validated.
+    pub fn polygons(&self) -> Box<dyn Iterator<Item = ArrayView1<'_, i32>> + '_> {
+        match self {
+            Faces::Uniform(faces) => Box::new(faces.outer_iter()),
+            Faces::Polygons { offsets, indices } => Box::new(
+                offsets
+                    .iter()
+                    .zip(offsets.iter().skip(1))
+                    .map(|(&start, &end)| indices.slice(s![start as usize..end as usize])),
+            ),
+        }
+    }
+}
+
+pub enum Scalars {
+    Vertex(Vec<f32>),
+    /// One value per triangle.
+    Face(Vec<f32>),
+}
+
+/// The geometry of an object as it was sent, kept so the object can be rebuilt
+/// with different settings.
+pub struct Geometry {
+    pub positions: Vec<Vec3>,
+    pub triangles: Vec<[u32; 3]>,
+    /// Corners of the faces as they were sent, face `i` being
+    /// `corners[offsets[i]..offsets[i + 1]]`, kept to draw their edges without
+    /// triangulation diagonals. `None` if all faces are triangles, which
+    /// `triangles` already holds.
+    pub polygons: Option<(Vec<u32>, Vec<u32>)>,
+    pub scalars: Option<Scalars>,
+    /// Normals sent by the client, which take precedence over the shading.
+    pub normals: Option<Vec<Vec3>>,
+    pub uvs: Option<Vec<Vec2>>,
+}
+
+impl Geometry {
+    /// Copies validated mesh data.
+    pub fn new(data: &MeshData) -> Self {
+        let positions: Vec<Vec3> = data
+            .verts
+            .outer_iter()
+            .map(|vert| Vec3::new(vert[0], vert[1], vert[2]))
+            .collect();
+        let mut triangles = vec![];
+        let mut triangle_faces = vec![];
+        let mut polygons = match &data.faces {
+            Faces::Uniform(faces) if faces.ncols() == 3 => None,
+            _ => Some((vec![0], vec![])),
+        };
+        for (face, polygon) in data.faces.polygons().enumerate() {
+            let polygon: Vec<u32> = polygon.iter().map(|&i| i as u32).collect();
+            triangulate(&positions, &polygon, &mut triangles);
+            triangle_faces.resize(triangles.len(), face);
+            if let Some((offsets, corners)) = &mut polygons {
+                corners.extend_from_slice(&polygon);
+                offsets.push(corners.len() as u32);
+            }
+        }
+        match data.winding {
+            Winding::Auto => orient(&positions, &mut triangles),
+            Winding::Ccw => {}
+            Winding::Cw => {
+                for triangle in &mut triangles {
+                    triangle.reverse();
+                }
+            }
+        }
+        let scalars = match (&data.vertex_scalars, &data.face_scalars) {
+            (Some(scalars), _) => Some(Scalars::Vertex(scalars.to_vec())),
+            (_, Some(scalars)) => Some(Scalars::Face(
+                triangle_faces.iter().map(|&face| scalars[face]).collect(),
+            )),
+            (None, None) => None,
+        };
+        let normals = data.normals.as_ref().map(|normals| {
+            normals
+                .outer_iter()
+                .map(|n| Vec3::new(n[0], n[1], n[2]).normalize_or_zero())
+                .collect()
+        });
+        let uvs = data
+            .uvs
+            .as_ref()
+            .map(|uvs| uvs.outer_iter().map(|uv| Vec2::new(uv[0], uv[1])).collect());
+        Self {
+            positions,
+            triangles,
+            polygons,
+            scalars,
+            normals,
+            uvs,
+        }
+    }
+
+    /// Edges of the faces as they were sent, each once and without
+    /// triangulation diagonals.
+    pub fn edges(&self) -> Vec<[u32; 2]> {
+        let polygons: Box<dyn Iterator<Item = &[u32]>> = match &self.polygons {
+            Some((offsets, corners)) => Box::new(
+                offsets
+                    .iter()
+                    .zip(offsets.iter().skip(1))
+                    .map(|(&start, &end)| &corners[start as usize..end as usize]),
+            ),
+            None => Box::new(self.triangles.iter().map(|triangle| &triangle[..])),
+        };
+        let mut edges = vec![];
+        let mut seen = HashSet::new();
+        for polygon in polygons {
+            let next = polygon.iter().cycle().skip(1);
+            for (&a, &b) in polygon.iter().zip(next) {
+                if seen.insert((a.min(b), a.max(b))) {
+                    edges.push([a, b]);
+                }
+            }
+        }
+        edges
+    }
+
+    /// Opposite corners of the axis-aligned box around the vertices.
+    pub fn bounds(&self) -> (Vec3, Vec3) {
+        let empty = (Vec3::splat(f32::INFINITY), Vec3::splat(f32::NEG_INFINITY));
+        self.positions
+            .iter()
+            .fold(empty, |(min, max), &p| (min.min(p), max.max(p)))
+    }
+
+    /// Face normals scaled by twice the face area.
+    fn weighted_face_normals(&self) -> Vec<Vec3> {
+        self.triangles
+            .iter()
+            .map(|&[a, b, c]| {
+                let [a, b, c] = [a, b, c].map(|i| self.positions[i as usize]);
+                (b - a).cross(c - a)
+            })
+            .collect()
+    }
+
+    /// Area weighted average of the normals of the faces around each vertex.
+    fn vertex_normals(&self, face_normals: &[Vec3]) -> Vec<Vec3> {
+        let mut normals = vec![Vec3::ZERO; self.positions.len()];
+        for (triangle, normal) in self.triangles.iter().zip(face_normals) {
+            for &i in triangle {
+                normals[i as usize] += *normal;
+            }
+        }
+        normals.iter().map(|n| n.normalize_or_zero()).collect()
+    }
+
+    /// Normal of each face corner, averaged only over the faces around the
+    /// vertex that are within `angle` of the corner's own face.
+    fn crease_normals(&self, face_normals: &[Vec3], angle: f32) -> Vec<[Vec3; 3]> {
+        let mut faces_of_vertex = vec![vec![]; self.positions.len()];
+        for (face, triangle) in self.triangles.iter().enumerate() {
+            for &i in triangle {
+                faces_of_vertex[i as usize].push(face);
+            }
+        }
+        let unit: Vec<Vec3> = face_normals.iter().map(|n| n.normalize_or_zero()).collect();
+        let min_cos = angle.to_radians().cos();
+        self.triangles
+            .iter()
+            .enumerate()
+            .map(|(face, triangle)| {
+                triangle.map(|i| {
+                    faces_of_vertex[i as usize]
+                        .iter()
+                        .filter(|&&other| unit[face].dot(unit[other]) >= min_cos)
+                        .map(|&other| face_normals[other])
+                        .sum::<Vec3>()
+                        .normalize_or_zero()
+                })
+            })
+            .collect()
+    }
+}
+
+/// Appends triangles covering `polygon` with the same orientation. Ear
+/// clipping in the plane of the polygon keeps concave faces right; faces too
+/// degenerate for that are split into a fan.
+fn triangulate(positions: &[Vec3], polygon: &[u32], triangles: &mut Vec<[u32; 3]>) {
+    if let [a, b, c] = *polygon {
+        triangles.push([a, b, c]);
+        return;
+    }
+
+    // Newell's method gives a normal that is robust for non-planar polygons.
+    let points: Vec<Vec3> = polygon.iter().map(|&i| positions[i as usize]).collect();
+    let next = points.iter().cycle().skip(1);
+    let normal: Vec3 = points.iter().zip(next).map(|(p, q)| p.cross(*q)).sum();
+    let mut remaining: Vec<usize> = (0..polygon.len()).collect();
+    if let Some(normal) = normal.try_normalize() {
+        let (u, v) = normal.any_orthonormal_pair();
+        let flat: Vec<Vec2> = points
+            .iter()
+            .map(|p| Vec2::new(p.dot(u), p.dot(v)))
+            .collect();
+        let area: f32 = remaining
+            .iter()
+            .map(|&i| flat[i].perp_dot(flat[(i + 1) % flat.len()]))
+            .sum();
+        let turn =
+            |a: usize, b: usize, c: usize| (flat[b] - flat[a]).perp_dot(flat[c] - flat[b]) * area;
+
+        while remaining.len() > 3 {
+            let n = remaining.len();
+            let corners = |i: usize| {
+                (
+                    remaining[(i + n - 1) % n],
+                    remaining[i],
+                    remaining[(i + 1) % n],
+                )
+            };
+            let is_ear = |i: usize| {
+                let (a, b, c) = corners(i);
+                turn(a, b, c) > 0.
+                    && !remaining.iter().any(|&p| {
+                        p != a
+                            && p != b
+                            && p != c
+                            && turn(a, b, p) >= 0.
+                            && turn(b, c, p) >= 0.
+                            && turn(c, a, p) >= 0.
+                    })
+            };
+            let Some(ear) = (0..n).find(|&i| is_ear(i)) else {
+                break;
+            };
+            let (a, b, c) = corners(ear);
+            triangles.push([polygon[a], polygon[b], polygon[c]]);
+            remaining.remove(ear);
+        }
+    }
+
+    for i in 1..remaining.len() - 1 {
+        triangles.push([
+            polygon[remaining[0]],
+            polygon[remaining[i]],
+            polygon[remaining[i + 1]],
+        ]);
+    }
+}
+
+/// The edges of a triangle in the direction it runs along them.
+fn directed_edges([a, b, c]: [u32; 3]) -> [(u32, u32); 3] {
+    [(a, b), (b, c), (c, a)]
+}
+
+/// Flips triangles so that neighbours agree on their orientation, then flips
+/// every connected piece whose signed volume is negative so it faces outward.
+fn orient(positions: &[Vec3], triangles: &mut [[u32; 3]]) {
+    let mut by_edge: HashMap<(u32, u32), Vec<usize>> = HashMap::new();
+    for (t, &triangle) in triangles.iter().enumerate() {
+        for (a, b) in directed_edges(triangle) {
+            by_edge.entry((a.min(b), a.max(b))).or_default().push(t);
+        }
+    }
+    let center = positions.iter().sum::<Vec3>() / positions.len() as f32;
+    let oriented = |triangle: [u32; 3], flip: bool| {
+        let [a, b, c] = triangle;
+        if flip {
+            [c, b, a]
+        } else {
+            [a, b, c]
+        }
+    };
+
+    let mut flip = vec![false; triangles.len()];
+    let mut visited = vec![false; triangles.len()];
+    for start in 0..triangles.len() {
+        if visited[start] {
+            continue;
+        }
+        visited[start] = true;
+        let mut piece = vec![start];
+        let mut stack = vec![start];
+        while let Some(t) = stack.pop() {
+            for (a, b) in directed_edges(oriented(triangles[t], flip[t])) {
+                let neighbours = &by_edge[&(a.min(b), a.max(b))];
+                // Edges shared by more than two faces say nothing about orientation.
+                if neighbours.len() != 2 {
+                    continue;
+                }
+                for &n in neighbours {
+                    if visited[n] {
+                        continue;
+                    }
+                    visited[n] = true;
+                    // A consistent neighbour runs along the shared edge the other way.
+                    flip[n] = directed_edges(triangles[n]).contains(&(a, b));
+                    piece.push(n);
+                    stack.push(n);
+                }
+            }
+        }
+
+        let volume: f32 = piece
+            .iter()
+            .map(|&t| {
+                let [a, b, c] =
+                    oriented(triangles[t], flip[t]).map(|i| positions[i as usize] - center);
+                a.dot(b.cross(c))
+            })
+            .sum();
+        if volume < 0. {
+            for &t in &piece {
+                flip[t] = !flip[t];
+            }
+        }
+    }
+
+    for (triangle, flip) in triangles.iter_mut().zip(flip) {
+        if flip {
+            triangle.reverse();
+        }
+    }
+}
+
+/// A mesh ready to be shown, with one scalar per mesh vertex if the object
+/// has scalars.
+pub struct Built {
+    pub mesh: Mesh,
+    pub scalars: Option<Vec<f32>>,
+}
+
+/// Turns geometry into an indexed mesh. Vertices are only duplicated where
+/// faces meeting at them need different normals or face scalars.
+pub fn build(geometry: &Geometry, shading: Shading) -> Built {
+    let face_normals = geometry.weighted_face_normals();
+    let has_face_scalars = matches!(geometry.scalars, Some(Scalars::Face(_)));
+
+    // Smooth shading and client normals never need to split vertices, so the
+    // geometry can be used as is.
+    if (shading == Shading::Smooth || geometry.normals.is_some()) && !has_face_scalars {
+        let normals = match &geometry.normals {
+            Some(normals) => normals.clone(),
+            None => geometry.vertex_normals(&face_normals),
+        };
+        let indices = geometry.triangles.iter().flatten().copied().collect();
+        let scalars = match &geometry.scalars {
+            Some(Scalars::Vertex(scalars)) => Some(scalars.clone()),
+            _ => None,
+        };
+        return Built {
+            mesh: indexed_mesh(
+                geometry.positions.clone(),
+                normals,
+                geometry.uvs.clone(),
+                indices,
+            ),
+            scalars,
+        };
+    }
+
+    let per_vertex = |normals: &[Vec3]| -> Vec<[Vec3; 3]> {
+        geometry
+            .triangles
+            .iter()
+            .map(|triangle| triangle.map(|i| normals[i as usize]))
+            .collect()
+    };
+    let corner_normals = match (&geometry.normals, shading) {
+        (Some(normals), _) => per_vertex(normals),
+        (None, Shading::Flat) => face_normals
+            .iter()
+            .map(|n| [n.normalize_or_zero(); 3])
+            .collect(),
+        (None, Shading::Smooth) => per_vertex(&geometry.vertex_normals(&face_normals)),
+        (None, Shading::Crease(angle)) => geometry.crease_normals(&face_normals, angle),
+    };
+
+    // Weld corners that share a vertex, a normal and, for face scalars, a value.
+    let mut welded = HashMap::new();
+    let mut positions = vec![];
+    let mut normals = vec![];
+    let mut sources = vec![];
+    let mut indices = Vec::with_capacity(geometry.triangles.len() * 3);
+    for (face, (triangle, corner_normals)) in
+        geometry.triangles.iter().zip(&corner_normals).enumerate()
+    {
+        for (&vertex, normal) in triangle.iter().zip(corner_normals) {
+            let value = match &geometry.scalars {
+                Some(Scalars::Face(scalars)) => scalars[face].to_bits(),
+                _ => 0,
+            };
+            let key = (vertex, normal.to_array().map(f32::to_bits), value);
+            let index = *welded.entry(key).or_insert_with(|| {
+                positions.push(geometry.positions[vertex as usize]);
+                normals.push(*normal);
+                sources.push((vertex as usize, face));
+                positions.len() as u32 - 1
+            });
+            indices.push(index);
+        }
+    }
+
+    let uvs = geometry
+        .uvs
+        .as_ref()
+        .map(|uvs| sources.iter().map(|&(v, _)| uvs[v]).collect());
+    let scalars = match &geometry.scalars {
+        Some(Scalars::Vertex(scalars)) => Some(sources.iter().map(|&(v, _)| scalars[v]).collect()),
+        Some(Scalars::Face(scalars)) => Some(sources.iter().map(|&(_, f)| scalars[f]).collect()),
+        None => None,
+    };
+    Built {
+        mesh: indexed_mesh(positions, normals, uvs, indices),
+        scalars,
+    }
+}
+
+/// Lines along the edges of the faces as they were sent. Copies the vertices,
+/// so it is only worth building while the edges are shown.
+pub fn edge_mesh(geometry: &Geometry) -> Mesh {
+    let mut mesh = Mesh::new(PrimitiveTopology::LineList);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, geometry.positions.clone());
+    // Required by the PBR pipeline, but unused as edges are drawn unlit.
+    mesh.insert_attribute(
+        Mesh::ATTRIBUTE_NORMAL,
+        vec![Vec3::ZERO; geometry.positions.len()],
+    );
+    let indices = geometry.edges().into_iter().flatten().collect();
+    mesh.set_indices(Some(Indices::U32(indices)));
+    mesh
+}
+
+fn indexed_mesh(
+    positions: Vec<Vec3>,
+    normals: Vec<Vec3>,
+    uvs: Option<Vec<Vec2>>,
+    indices: Vec<u32>,
+) -> Mesh {
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    if let Some(uvs) = uvs {
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    }
+    mesh.set_indices(Some(Indices::U32(indices)));
+    mesh
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::render::mesh::VertexAttributeValues;
+
+    fn plane(points: &[[f32; 2]]) -> Vec<Vec3> {
+        points.iter().map(|&[x, y]| Vec3::new(x, y, 0.)).collect()
+    }
+
+    /// Twice the signed area of each triangle, as seen from +Z.
+    fn signed_areas(positions: &[Vec3], triangles: &[[u32; 3]]) -> Vec<f32> {
+        triangles
+            .iter()
+            .map(|&[a, b, c]| {
+                let [a, b, c] = [a, b, c].map(|i| positions[i as usize]);
+                (b - a).cross(c - a).z
+            })
+            .collect()
+    }
+
+    fn triangulated(positions: &[Vec3]) -> Vec<[u32; 3]> {
+        let polygon: Vec<u32> = (0..positions.len() as u32).collect();
+        let mut triangles = vec![];
+        triangulate(positions, &polygon, &mut triangles);
+        assert_eq!(triangles.len(), polygon.len() - 2);
+        triangles
+    }
+
+    #[test]
+    fn concave_quad() {
+        // An arrowhead with its reflex corner at (1, 1).
+        let positions = plane(&[[0., 0.], [2., 1.], [0., 2.], [1., 1.]]);
+        let areas = signed_areas(&positions, &triangulated(&positions));
+        assert!(areas.iter().all(|&area| area > 0.));
+        assert_eq!(areas.iter().sum::<f32>(), 2.);
+    }
+
+    #[test]
+    fn l_shape_keeps_orientation() {
+        let mut points = [[0., 0.], [2., 0.], [2., 1.], [1., 1.], [1., 2.], [0., 2.]];
+        let positions = plane(&points);
+        let areas = signed_areas(&positions, &triangulated(&positions));
+        assert!(areas.iter().all(|&area| area > 0.));
+        assert_eq!(areas.iter().sum::<f32>(), 6.);
+
+        points.reverse();
+        let positions = plane(&points);
+        let areas = signed_areas(&positions, &triangulated(&positions));
+        assert!(areas.iter().all(|&area| area < 0.));
+        assert_eq!(areas.iter().sum::<f32>(), -6.);
+    }
+
+    #[test]
+    fn degenerate_polygon() {
+        let collinear = plane(&[[0., 0.], [1., 0.], [2., 0.], [3., 0.], [4., 0.]]);
+        triangulated(&collinear);
+        let repeated = plane(&[[0., 0.], [0., 0.], [1., 0.], [1., 0.]]);
+        triangulated(&repeated);
+    }
+
+    /// A unit square made of two triangles.
+    fn square() -> MeshData {
+        let verts = ndarray::array![[0., 0., 0.], [1., 0., 0.], [1., 1., 0.], [0., 1., 0.]];
+        MeshData::new("square", verts, ndarray::array![[0, 1, 2], [0, 2, 3]])
+    }
+
+    /// The vertices of [`square`] with the given polygons.
+    fn square_polygons(offsets: &[i32], indices: &[i32]) -> MeshData {
+        let faces = Faces::Polygons {
+            offsets: offsets.iter().copied().collect(),
+            indices: indices.iter().copied().collect(),
+        };
+        MeshData::new("polygons", square().verts, faces)
+    }
+
+    #[test]
+    fn valid_meshes() {
+        assert!(validate(&square()).is_ok());
+        assert!(validate(&square_polygons(&[0, 4], &[0, 1, 2, 3])).is_ok());
+    }
+
+    #[test]
+    fn index_out_of_range() {
+        for index in [-1, 4] {
+            let mut data = square();
+            let Faces::Uniform(faces) = &mut data.faces else {
+                unreachable!()
+            };
+            faces[[1, 2]] = index;
+            assert!(matches!(
+                validate(&data),
+                Err(MeshError::IndexOutOfRange {
+                    face: 1,
+                    n_verts: 4,
+                    ..
+                })
+            ));
+        }
+    }
+
+    #[test]
+    fn bad_shapes() {
+        let verts = ndarray::array![[0., 0.], [1., 0.], [1., 1.]];
+        let data = MeshData::new("flat", verts, ndarray::array![[0, 1, 2]]);
+        assert!(matches!(
+            validate(&data),
+            Err(MeshError::VertsShape([3, 2]))
+        ));
+
+        let data = MeshData::new("lines", square().verts, ndarray::array![[0, 1], [2, 3]]);
+        assert!(matches!(
+            validate(&data),
+            Err(MeshError::FacesShape([2, 2]))
+        ));
+    }
+
+    #[test]
+    fn bad_offsets() {
+        let data = square_polygons(&[1, 4], &[0, 1, 2, 3]);
+        assert!(matches!(validate(&data), Err(MeshError::OffsetBounds(4))));
+        let data = square_polygons(&[0, 3], &[0, 1, 2, 3]);
+        assert!(matches!(validate(&data), Err(MeshError::OffsetBounds(4))));
+        let data = square_polygons(&[0, 3, 5], &[0, 1, 2, 2, 3]);
+        assert!(matches!(
+            validate(&data),
+            Err(MeshError::TooFewCorners {
+                face: 1,
+                corners: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn non_finite_coordinates() {
+        for value in [f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+            let mut data = square();
+            data.verts[[2, 1]] = value;
+            assert!(matches!(
+                validate(&data),
+                Err(MeshError::NonFinite { index: 2, .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn bad_scalars() {
+        let mut data = square();
+        data.vertex_scalars = Some(ndarray::array![0., 1., 2.]);
+        assert!(matches!(
+            validate(&data),
+            Err(MeshError::ScalarsLength {
+                kind: "vertex",
+                expected: 4,
+                got: 3
+            })
+        ));
+
+        let mut data = square();
+        data.face_scalars = Some(ndarray::array![0., 1., 2.]);
+        assert!(matches!(
+            validate(&data),
+            Err(MeshError::ScalarsLength {
+                kind: "face",
+                expected: 2,
+                got: 3
+            })
+        ));
+
+        let mut data = square();
+        data.vertex_scalars = Some(ndarray::array![0., 1., 2., 3.]);
+        data.face_scalars = Some(ndarray::array![0., 1.]);
+        assert!(matches!(validate(&data), Err(MeshError::BothScalars)));
+    }
+
+    fn sorted_edges(geometry: &Geometry) -> Vec<[u32; 2]> {
+        let mut edges: Vec<_> = geometry
+            .edges()
+            .into_iter()
+            .map(|[a, b]| [a.min(b), a.max(b)])
+            .collect();
+        edges.sort();
+        edges
+    }
+
+    #[test]
+    fn edges_skip_diagonals() {
+        // A quad and a triangle sharing the edge 1-2.
+        let verts = ndarray::array![
+            [0., 0., 0.],
+            [1., 0., 0.],
+            [1., 1., 0.],
+            [0., 1., 0.],
+            [2., 0., 0.]
+        ];
+        let faces = Faces::Polygons {
+            offsets: ndarray::array![0, 4, 7],
+            indices: ndarray::array![0, 1, 2, 3, 1, 4, 2],
+        };
+        let geometry = Geometry::new(&MeshData::new("polygons", verts.clone(), faces));
+        assert_eq!(geometry.triangles.len(), 3);
+        assert_eq!(
+            sorted_edges(&geometry),
+            [[0, 1], [0, 3], [1, 2], [1, 4], [2, 3], [2, 4]]
+        );
+
+        let faces = ndarray::array![[0, 1, 2], [0, 2, 3]];
+        let geometry = Geometry::new(&MeshData::new("triangles", verts, faces));
+        assert!(geometry.polygons.is_none());
+        assert_eq!(
+            sorted_edges(&geometry),
+            [[0, 1], [0, 2], [0, 3], [1, 2], [2, 3]]
+        );
+    }
+
+    /// A cube around the origin with faces wound counter-clockwise seen from
+    /// outside.
+    fn cube() -> (Vec<Vec3>, Vec<[u32; 3]>) {
+        let sign = |i: u32, bit: u32| if i & bit != 0 { 1. } else { -1. };
+        let positions = (0..8)
+            .map(|i| Vec3::new(sign(i, 1), sign(i, 2), sign(i, 4)))
+            .collect();
+        let quads = [
+            [0, 2, 3, 1],
+            [4, 5, 7, 6],
+            [0, 1, 5, 4],
+            [2, 6, 7, 3],
+            [0, 4, 6, 2],
+            [1, 3, 7, 5],
+        ];
+        let triangles = quads
+            .iter()
+            .flat_map(|&[a, b, c, d]| [[a, b, c], [a, c, d]])
+            .collect();
+        (positions, triangles)
+    }
+
+    fn signed_volume(positions: &[Vec3], triangles: &[[u32; 3]]) -> f32 {
+        triangles
+            .iter()
+            .map(|&[a, b, c]| {
+                let [a, b, c] = [a, b, c].map(|i| positions[i as usize]);
+                a.dot(b.cross(c)) / 6.
+            })
+            .sum()
+    }
+
+    /// Whether every edge is walked once in each direction.
+    fn consistent(triangles: &[[u32; 3]]) -> bool {
+        let mut seen = HashSet::new();
+        let edges: Vec<_> = triangles.iter().flat_map(|&t| directed_edges(t)).collect();
+        edges.iter().all(|&edge| seen.insert(edge))
+            && edges.iter().all(|&(a, b)| seen.contains(&(b, a)))
+    }
+
+    #[test]
+    fn orient_mixed_cube() {
+        let (positions, mut triangles) = cube();
+        assert!((signed_volume(&positions, &triangles) - 8.).abs() < 1e-4);
+        for triangle in triangles.iter_mut().step_by(3) {
+            triangle.reverse();
+        }
+        orient(&positions, &mut triangles);
+        assert!(consistent(&triangles));
+        assert!((signed_volume(&positions, &triangles) - 8.).abs() < 1e-4);
+    }
+
+    #[test]
+    fn orient_inside_out_cube() {
+        let (positions, mut triangles) = cube();
+        for triangle in &mut triangles {
+            triangle.reverse();
+        }
+        orient(&positions, &mut triangles);
+        assert!(consistent(&triangles));
+        assert!((signed_volume(&positions, &triangles) - 8.).abs() < 1e-4);
+    }
+
+    #[test]
+    fn orient_open_and_non_manifold() {
+        // A cube missing its +Z face, with one triangle flipped.
+        let (positions, mut triangles) = cube();
+        triangles.drain(2..4);
+        triangles[0].reverse();
+        orient(&positions, &mut triangles);
+        let edges: Vec<_> = triangles.iter().flat_map(|&t| directed_edges(t)).collect();
+        let mut seen = HashSet::new();
+        assert!(edges.iter().all(|&edge| seen.insert(edge)));
+
+        // Three triangles on one edge.
+        let positions = vec![Vec3::ZERO, Vec3::X, Vec3::Y, Vec3::Z, Vec3::NEG_Y];
+        let mut triangles = vec![[0, 1, 2], [1, 0, 3], [0, 1, 4]];
+        orient(&positions, &mut triangles);
+        assert_eq!(triangles.len(), 3);
+    }
+
+    fn cube_geometry() -> Geometry {
+        let (positions, triangles) = cube();
+        Geometry {
+            positions,
+            triangles,
+            polygons: None,
+            scalars: None,
+            normals: None,
+            uvs: None,
+        }
+    }
+
+    fn normals(mesh: &Mesh) -> Vec<Vec3> {
+        match mesh.attribute(Mesh::ATTRIBUTE_NORMAL) {
+            Some(VertexAttributeValues::Float32x3(normals)) => {
+                normals.iter().map(|&n| Vec3::from(n)).collect()
+            }
+            other => panic!("unexpected normals {:?}", other),
+        }
+    }
+
+    fn indices(mesh: &Mesh) -> Vec<u32> {
+        match mesh.indices() {
+            Some(Indices::U32(indices)) => indices.clone(),
+            other => panic!("expected 32 bit indices, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn crease_splits_cube_corners() {
+        let built = build(&cube_geometry(), Shading::Crease(30.));
+        assert_eq!(built.mesh.count_vertices(), 24);
+        assert_eq!(indices(&built.mesh).len(), 36);
+        // Each corner takes the normal of its own side only.
+        for normal in normals(&built.mesh) {
+            let axis = normal.abs();
+            assert_eq!(axis.max_element(), 1.);
+            assert_eq!(axis.min_element(), 0.);
+        }
+    }
+
+    #[test]
+    fn smooth_cube_shares_corners() {
+        let geometry = cube_geometry();
+        let built = build(&geometry, Shading::Smooth);
+        assert_eq!(built.mesh.count_vertices(), 8);
+        assert_eq!(indices(&built.mesh).len(), 36);
+        // Averaged over the three sides, so leaning outwards along every axis.
+        for (normal, position) in normals(&built.mesh).iter().zip(&geometry.positions) {
+            assert!((normal.length() - 1.).abs() < 1e-5);
+            assert_eq!(normal.signum(), position.signum());
+        }
+    }
+
+    #[test]
+    fn shared_edge_is_welded() {
+        let geometry = Geometry::new(&square());
+        let built = build(&geometry, Shading::Flat);
+        assert_eq!(built.mesh.count_vertices(), 4);
+        let indices = indices(&built.mesh);
+        assert_eq!(indices.len(), 6);
+        // Corners of both triangles on the same vertex share an index.
+        for (i, &a) in geometry.triangles.iter().flatten().enumerate() {
+            for (j, &b) in geometry.triangles.iter().flatten().enumerate() {
+                assert_eq!(a == b, indices[i] == indices[j]);
+            }
+        }
+    }
+
+    #[test]
+    fn face_scalars_split_shared_corners() {
+        let mut data = square();
+        data.face_scalars = Some(ndarray::array![0., 1.]);
+        let built = build(&Geometry::new(&data), Shading::Smooth);
+        assert_eq!(built.mesh.count_vertices(), 6);
+        let scalars = built.scalars.unwrap();
+        let indices = indices(&built.mesh);
+        assert_eq!(indices.len(), 6);
+        for (corner, &index) in indices.iter().enumerate() {
+            assert_eq!(scalars[index as usize], (corner / 3) as f32);
+        }
+    }
+}
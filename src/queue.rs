@@ -0,0 +1,145 @@
+use std::collections::{HashSet, VecDeque};
+use std::hash::Hash;
+use std::str::FromStr;
+use std::sync::{Condvar, Mutex};
+
+/// What a full [`Queue`] does with new items.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backpressure {
+    /// Wait until the consumer has made room.
+    Block,
+    /// Evict the oldest queued item to make room.
+    DropOldest,
+}
+
+impl FromStr for Backpressure {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "block" => Ok(Self::Block),
+            "drop-oldest" => Ok(Self::DropOldest),
+            _ => Err(format!(
+                "unknown backpressure policy {:?}, expected \"block\" or \"drop-oldest\"",
+                s
+            )),
+        }
+    }
+}
+
+/// A bounded multi-producer queue that the consumer empties in one go.
+pub struct Queue<T> {
+    items: Mutex<VecDeque<T>>,
+    not_full: Condvar,
+    capacity: usize,
+    policy: Backpressure,
+}
+
+impl<T> Queue<T> {
+    pub fn new(capacity: usize, policy: Backpressure) -> Self {
+        Self {
+            // Grows as needed; `capacity` only bounds it, so any value is fine.
+            items: Mutex::new(VecDeque::new()),
+            not_full: Condvar::new(),
+            capacity: capacity.max(1),
+            policy,
+        }
+    }
+
+    /// Queues `item`, returning whatever had to be evicted to make room for it.
+    pub fn push(&self, item: T) -> Option<T> {
+        let mut items = self.items.lock().unwrap();
+        let mut evicted = None;
+        while items.len() >= self.capacity {
+            match self.policy {
+                Backpressure::Block => items = self.not_full.wait(items).unwrap(),
+                Backpressure::DropOldest => evicted = items.pop_front(),
+            }
+        }
+        items.push_back(item);
+        evicted
+    }
+
+    /// Takes everything queued so far, oldest first.
+    pub fn drain(&self) -> Vec<T> {
+        let drained = self.items.lock().unwrap().drain(..).collect();
+        self.not_full.notify_all();
+        drained
+    }
+}
+
+/// Drops every item that a later item with the same key supersedes, keeping
+/// the order of the rest. Items without a key are always kept and act as
+/// barriers: nothing before them is superseded by anything after them.
+pub fn coalesce<T, K: Eq + Hash>(items: Vec<T>, key: impl Fn(&T) -> Option<K>) -> Vec<T> {
+    let mut seen = HashSet::new();
+    let mut kept: Vec<T> = items
+        .into_iter()
+        .rev()
+        .filter(|item| match key(item) {
+            Some(k) => seen.insert(k),
+            None => {
+                seen.clear();
+                true
+            }
+        })
+        .collect();
+    kept.reverse();
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Items are numbered, with an optional key.
+    fn coalesced(items: &[(Option<char>, u32)]) -> Vec<u32> {
+        coalesce(items.to_vec(), |&(key, _)| key)
+            .into_iter()
+            .map(|(_, n)| n)
+            .collect()
+    }
+
+    #[test]
+    fn later_item_supersedes() {
+        let items = [
+            (Some('a'), 0),
+            (Some('b'), 1),
+            (Some('a'), 2),
+            (Some('c'), 3),
+        ];
+        assert_eq!(coalesced(&items), [1, 2, 3]);
+    }
+
+    #[test]
+    fn keyless_item_is_a_barrier() {
+        let items = [(Some('a'), 0), (None, 1), (Some('a'), 2), (None, 3)];
+        assert_eq!(coalesced(&items), [0, 1, 2, 3]);
+        let items = [
+            (Some('a'), 0),
+            (Some('a'), 1),
+            (None, 2),
+            (Some('a'), 3),
+            (Some('a'), 4),
+        ];
+        assert_eq!(coalesced(&items), [1, 2, 4]);
+    }
+
+    #[test]
+    fn drop_oldest_evicts_at_capacity() {
+        let queue = Queue::new(2, Backpressure::DropOldest);
+        assert_eq!(queue.push(0), None);
+        assert_eq!(queue.push(1), None);
+        assert_eq!(queue.push(2), Some(0));
+        assert_eq!(queue.push(3), Some(1));
+        assert_eq!(queue.drain(), [2, 3]);
+        assert_eq!(queue.push(4), None);
+    }
+
+    #[test]
+    fn huge_capacity_allocates_nothing_up_front() {
+        let queue = Queue::new(usize::MAX, Backpressure::Block);
+        assert_eq!(queue.push(0), None);
+        assert_eq!(queue.drain(), [0]);
+    }
+}